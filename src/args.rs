@@ -16,21 +16,19 @@ pub trait ArgParser<'a, T> {
     fn try_parse(input: &'a[String], used: &mut [bool]) -> Option<T> {
         input.iter().enumerate().filter_map(|(idx, it)| {
             for name in Self::NAMES {
-                if it.starts_with(name) {
-                    let rest = &it[name.len()..];
+                if let Some(rest) = it.strip_prefix(name) {
                     return if rest.is_empty() {
                         Some((idx, it, Self::parse_arg_value(None).map_err(|err| arg_err(idx, it, err))))
-                    } else if rest.starts_with("=") {
-                        Some((idx, it, Self::parse_arg_value(Some(&rest[1..])).map_err(|err|arg_err(idx, it, err))))
+                    } else if let Some(value) = rest.strip_prefix('=') {
+                        Some((idx, it, Self::parse_arg_value(Some(value)).map_err(|err|arg_err(idx, it, err))))
                     } else {
                         None
                     };
                 }
             }
             None
-        }).map(|it| {
+        }).inspect(|it| {
             used[it.0] = true;
-            it
         }).fold(None, |value, (idx, it, res)| {
             match res {
                 Ok(ok) => {
@@ -89,6 +87,11 @@ impl<'a, T: BoolParser<'a>> DefaultArgParser<'a, bool> for T {
 pub trait F64Parser<'a>: ArgParser<'a, f64> {
     const NAMES: &'static [&'static str];
     const DESCRIPTION: &'static str;
+
+    /// Why a value is out of range, the arg is then reported and ignored
+    fn check(_value: f64) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 impl<'a, T: F64Parser<'a>> ArgParser<'a, f64> for T {
@@ -99,6 +102,30 @@ impl<'a, T: F64Parser<'a>> ArgParser<'a, f64> for T {
     fn parse_arg_value(value: Option<&str>) -> Result<f64, String> {
         if let Some(value) = value {
             match f64::from_str(value) {
+                Ok(value) => <T as F64Parser>::check(value).map(|_| value),
+                Err(err) => {
+                    Err(format!("\"{}\": {}", value, err))
+                }
+            }
+        } else {
+            Err("Arg value is not optional, --help for more info".into())
+        }
+    }
+}
+
+pub trait UsizeParser<'a>: ArgParser<'a, usize> {
+    const NAMES: &'static [&'static str];
+    const DESCRIPTION: &'static str;
+}
+
+impl<'a, T: UsizeParser<'a>> ArgParser<'a, usize> for T {
+    const NAMES: &'static [&'static str] = <T as UsizeParser>::NAMES;
+    const VALUES: &'static [&'static str] = &["<int>"];
+    const DESCRIPTION: &'static str = <T as UsizeParser>::DESCRIPTION;
+
+    fn parse_arg_value(value: Option<&str>) -> Result<usize, String> {
+        if let Some(value) = value {
+            match usize::from_str(value) {
                 Ok(value) => Ok(value),
                 Err(err) => {
                     Err(format!("\"{}\": {}", value, err))
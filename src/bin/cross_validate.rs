@@ -0,0 +1,124 @@
+use std::{env, process};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ft_linear_regression::args::{ArgParser, DefaultArgParser, arg_err};
+use ft_linear_regression::dataset::{DatasetArg, Dataset, ReadOptions};
+use ft_linear_regression::scaler::ScalerArg;
+use ft_linear_regression::solver::{LearnRatioArg, SolverArg, BatchSizeArg, Solver, GdParams, Batching, fit};
+use ft_linear_regression::penalty::Penalty;
+use ft_linear_regression::random::{SeedArg, seed_from_time};
+use ft_linear_regression::optimizer::OptimizerKind;
+use ft_linear_regression::schedule::Schedule;
+use ft_linear_regression::convergence::{StopCriteria, StopReason};
+use ft_linear_regression::metrics::{SplitSeedArg, Metrics, table};
+use ft_linear_regression::validation::{FoldsArg, RepeatsArg, LeaveOneOutArg, cross_validate, mean_std};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).map(|it| it.to_lowercase()).collect();
+    let mut used = vec![false; args.len()];
+    let dataset_path = DatasetArg::try_parse(&args, &mut used);
+    let read_options = ReadOptions::parse(&args, &mut used);
+    let ratio = LearnRatioArg::parse(&args, &mut used);
+    let scaler_kind = ScalerArg::parse(&args, &mut used);
+    let solver = SolverArg::parse(&args, &mut used);
+    let criteria = StopCriteria::parse_capped(&args, &mut used);
+    let batch_size = BatchSizeArg::try_parse(&args, &mut used);
+    let seed = SeedArg::try_parse(&args, &mut used);
+    let optimizer = OptimizerKind::parse(&args, &mut used);
+    let schedule = Schedule::parse(&args, &mut used);
+    let penalty = Penalty::parse(&args, &mut used);
+    let folds = FoldsArg::try_parse(&args, &mut used);
+    let repeats = RepeatsArg::try_parse(&args, &mut used);
+    let leave_one_out = LeaveOneOutArg::parse(&args, &mut used);
+    let split_seed = SplitSeedArg::try_parse(&args, &mut used);
+
+    // invalid values are all reported before giving up
+    let mut invalid = false;
+    if solver == Solver::GradientDescent && (ratio >= 1.0 || ratio <= 0.0) {
+        println!("Error: Learning ratio must be 0 < R < 1");
+        invalid = true;
+    }
+    if batch_size == Some(0) {
+        println!("Error: Batch size must be at least 1");
+        invalid = true;
+    }
+    if repeats == Some(0) {
+        println!("Error: Repeats must be at least 1");
+        invalid = true;
+    }
+    if leave_one_out && (folds.is_some() || repeats.is_some()) {
+        println!("Warning: Leave-one-out makes one fold per row, ignoring --folds and --repeats");
+    }
+    if solver == Solver::Ols && penalty.l1() != 0.0 {
+        println!("Error: The closed form only handles ridge, use --solver cd or gd for {}", penalty);
+        invalid = true;
+    }
+    let batching = batch_size.map(|batch_size| Batching {
+        batch_size,
+        seed: seed.map_or_else(seed_from_time, |it| it as u64),
+    });
+    let params = GdParams { ratio, criteria, optimizer, schedule, penalty, batching, checkpoint_every: None, verbose: false };
+
+    for (idx, it) in args.iter().enumerate() {
+        if !used[idx] {
+            arg_err(idx, it, "Arg is not recognized, ignoring. --help for more info");
+        }
+    }
+    if invalid {
+        process::exit(1);
+    }
+
+    let dataset = match Dataset::read_from(dataset_path, &read_options) {
+        Ok((dataset, report)) => {
+            if !report.skipped.is_empty() {
+                println!("Warning: {}", report);
+            }
+            dataset
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            process::exit(1);
+        }
+    };
+    let (folds, repeats) = if leave_one_out {
+        (dataset.entries.len(), 1)
+    } else {
+        (folds.unwrap_or(FoldsArg::DEFAULT), repeats.unwrap_or(RepeatsArg::DEFAULT))
+    };
+    let split_seed = split_seed.map_or_else(seed_from_time, |it| it as u64);
+    println!("{} folds of {} rows, {} repeats, split seed {}", folds, dataset.entries.len(), repeats, split_seed);
+
+    // metrics of models the iteration cap stopped are of coefficients that had not converged yet
+    let capped = AtomicUsize::new(0);
+    let train = |rows: &Dataset| fit(rows, scaler_kind, solver, &params).inspect(|model| {
+        if model.training.as_ref().and_then(|it| it.stop) == Some(StopReason::MaxIterations) {
+            capped.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    let results = match cross_validate(&dataset, folds, repeats, split_seed, train) {
+        Ok(results) => results,
+        Err(err) => {
+            println!("Error: Cross-validation failed: {}", err);
+            process::exit(1);
+        }
+    };
+
+    println!("{:>6} | {:>4} | {:>4} | {:>14} | {:>14} | {:>14} | {:>10} | {:>10}", "repeat", "fold", "rows", "train RMSE", "test RMSE", "test MAE", "test MAPE", "test R²");
+    for it in &results {
+        println!("{:>6} | {:>4} | {:>4} | {:>14.6} | {:>14.6} | {:>14.6} | {:>10.4} | {:>10.6}", it.repeat + 1, it.fold + 1, it.test.count, it.train.rmse, it.test.rmse, it.test.mae, it.test.mape, it.test.r2);
+    }
+
+    let train: Vec<_> = results.iter().map(|it| it.train).collect();
+    let test: Vec<_> = results.iter().map(|it| it.test).collect();
+    let (train_mean, train_std) = mean_std(&train);
+    let (test_mean, test_std) = mean_std(&test);
+    // every prediction made on held out rows at once, the only R² leave-one-out has
+    let pooled: Vec<_> = results.iter().flat_map(|it| it.predictions.iter().copied()).collect();
+    let pooled = Metrics::compute(&pooled, dataset.features.len());
+    println!("{}", table(&[("train mean", &train_mean), ("train std", &train_std), ("test mean", &test_mean), ("test std", &test_std), ("test pooled", &pooled)]));
+    let capped = capped.into_inner();
+    if capped != 0 {
+        println!("Warning: {} of {} models stopped after {} iterations without converging, raise --max-iterations",
+            capped, results.len(), params.criteria.max_iterations.unwrap_or_default());
+    }
+}
+
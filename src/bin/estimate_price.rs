@@ -1,28 +1,173 @@
-use ft_linear_regression::estimate_price::estimate_price;
 use std::{env, io};
-use ft_linear_regression::args::{arg_err, ArgParser};
-use std::str::FromStr;
+use ft_linear_regression::args::{arg_err, ArgParser, DefaultArgParser};
 use std::cmp::max;
-use ft_linear_regression::theta::{get_theta, ThetaFileArg};
+use ft_linear_regression::theta::{get_theta, ThetaFileArg, Model};
+use ft_linear_regression::inference::ConfidenceArg;
+use ft_linear_regression::range::StrictArg;
+use ft_linear_regression::locale::NumberFormat;
+use ft_linear_regression::estimate_price::{InverseArg, SolveForArg, estimate_feature};
+use std::process;
 use std::io::{Write, BufRead};
 
+/// Formats feature values with their names as units, "240000.000 km, 5.000 age"
+fn describe(model: &Model, features: &[f64]) -> String {
+    features.iter().zip(&model.features).map(|(value, name)| format!("{:.3} {}", value, name)).collect::<Vec<_>>().join(", ")
+}
+
+/// The confidence interval of the mean price and the prediction interval of a single price at `level`, empty without interval statistics
+fn intervals(model: &Model, features: &[f64], level: f64) -> String {
+    let price = model.predict(features);
+    match model.interval_stats.as_ref().map(|it| it.half_widths(features, level)) {
+        Some(Ok((confidence, prediction))) => format!(" ({}% confidence {:.2} to {:.2} $, prediction {:.2} to {:.2} $)",
+            (level * 1e4).round() / 1e2, price - confidence, price + confidence, price - prediction, price + prediction),
+        Some(Err(err)) => format!(" (no interval: {})", err),
+        None => String::new(),
+    }
+}
+
+/// Whether `features` are within the training range, as ", interpolated" or ", extrapolated: why",
+/// noting in range values where the training rows are sparse.
+/// Errors with why when `strict` and out of range, empty for models without a training range.
+fn label(model: &Model, features: &[f64], strict: bool) -> Result<String, String> {
+    match &model.range {
+        Some(range) => {
+            let outside = range.outside(&model.features, features);
+            let sparse = range.sparse(&model.features, features);
+            match (outside.is_empty(), strict) {
+                (true, _) if sparse.is_empty() => Ok(", interpolated".into()),
+                (true, _) => Ok(format!(", interpolated with few training rows around: {}", sparse.join(", "))),
+                (false, false) => Ok(format!(", extrapolated: {}", outside.join(", "))),
+                (false, true) => Err(outside.join(", ")),
+            }
+        }
+        None => Ok(String::new()),
+    }
+}
+
+/// Parses a line of ';' separated values for the features `names`, either all positional in order or all as name=value pairs
+fn parse_features(names: &[String], line: &str, numbers: &NumberFormat) -> Result<Vec<f64>, String> {
+    let parts: Vec<_> = line.split(';').map(str::trim).collect();
+    let parse = |value: &str| numbers.parse_number(value);
+    if parts.iter().any(|it| it.contains('=')) {
+        let mut features = vec![None; names.len()];
+        for part in parts {
+            let (name, value) = part.split_at(part.find('=').ok_or_else(|| format!("\"{}\" is not a name=value pair", part))?);
+            let idx = names.iter().position(|it| it.eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| format!("Unknown feature \"{}\", expected {}", name.trim(), names.join(", ")))?;
+            if features[idx].replace(parse(value[1..].trim())?).is_some() {
+                return Err(format!("Feature \"{}\" is set twice", names[idx]));
+            }
+        }
+        features.into_iter().zip(names)
+            .map(|(value, name)| value.ok_or_else(|| format!("Feature \"{}\" is missing", name)))
+            .collect()
+    } else if parts.len() != names.len() {
+        Err(format!("Expected {} values ({}), got {}", names.len(), names.join(", "), parts.len()))
+    } else {
+        parts.into_iter().map(parse).collect()
+    }
+}
+
+/// What a line or a group of args asks for
+struct Query {
+    /// Some feature to solve for, None to price
+    solved: Option<usize>,
+    level: f64,
+    strict: bool,
+}
+
+impl Query {
+    /// Features then price, or price then features when solving. The rest of the line is the details, like intervals.
+    fn answer(&self, model: &Model, values: &[f64]) -> Result<(String, String, String), String> {
+        match self.solved {
+            None => {
+                let label = label(model, values, self.strict).map_err(|outside| format!("Refusing to price {} outside the training range: {}", describe(model, values), outside))?;
+                Ok((describe(model, values), format!("{:.2} $", model.predict(values)), format!("{}{}", intervals(model, values, self.level), label)))
+            }
+            Some(solved) => {
+                let (price, mut features) = (values[0], values[1..].to_vec());
+                features.insert(solved, 0.0);
+                let theta = model.raw_theta();
+                features[solved] = estimate_feature(price, &features, solved, &theta)
+                    .ok_or_else(|| format!("The price does not depend on {}, no {} gives {:.2} $", model.features[solved], model.features[solved], price))?;
+                let label = label(model, &features, self.strict).map_err(|outside| format!("Refusing to solve for {:.2} $ outside the training range: {}", price, outside))?;
+                let interval = match model.interval_stats.as_ref().map(|it| it.inverse_interval(&theta, price, &features, solved, self.level)) {
+                    Some(Ok(Some((low, high)))) => format!(" ({}% interval {:.3} to {:.3} {})", (self.level * 1e4).round() / 1e2, low, high, model.features[solved]),
+                    Some(Ok(None)) => format!(" ({}% interval is unbounded, {} barely moves the price)", (self.level * 1e4).round() / 1e2, model.features[solved]),
+                    Some(Err(err)) => format!(" (no interval: {})", err),
+                    None => String::new(),
+                };
+                Ok((format!("{:.2} $", price), describe(model, &features), format!("{}{}", interval, label)))
+            }
+        }
+    }
+
+    /// Joins the parts of [Query::answer]
+    fn verb(&self) -> &'static str {
+        if self.solved.is_some() { "is the price at" } else { "is priced" }
+    }
+}
 
 fn main() {
     let args: Vec<_> = env::args().skip(1).map(|it|it.to_lowercase()).collect();
     let mut used = vec![false; args.len()];
     let theta_path = ThetaFileArg::try_parse(&args, &mut used);
-    let theta = get_theta(theta_path);
+    let mut level = ConfidenceArg::try_parse(&args, &mut used);
+    let strict = StrictArg::parse(&args, &mut used);
+    let inverse = InverseArg::parse(&args, &mut used);
+    let solve_for = SolveForArg::try_parse(&args, &mut used);
+    let numbers = NumberFormat::parse(&args, &mut used);
+    let model = get_theta(theta_path);
+    if model.partial {
+        println!("Warning: This model's training was interrupted, predictions may be off");
+    }
+    if strict && model.range.is_none() {
+        println!("Error: This model has no training range to check inputs against, train it again to use --strict");
+        process::exit(1);
+    }
+    if level.is_some_and(|it| it <= 0.0 || it >= 1.0) {
+        println!("Error: Confidence must be 0 < C < 1");
+        level = None;
+    }
+    if level.is_some() && model.interval_stats.is_none() {
+        println!("Warning: This model has no interval statistics, train it again to get intervals");
+    }
+    if solve_for.is_some() && !inverse {
+        println!("Warning: Solving for a feature is only done with --inverse, ignoring");
+    }
+    let solved = match solve_for.filter(|_| inverse) {
+        Some(name) => match model.features.iter().position(|it| it.eq_ignore_ascii_case(name)) {
+            Some(idx) => Some(idx),
+            None => {
+                println!("Error: Unknown feature \"{}\", model expects {}", name, model.features.join(", "));
+                process::exit(1);
+            }
+        },
+        None if inverse => Some(0),
+        None => None,
+    };
+    // features given along with each price when solving
+    let others: Vec<_> = model.features.iter().enumerate().filter(|(idx, _)| Some(*idx) != solved).map(|(_, it)| it.clone()).collect();
+    let mut query = Query { solved, level: level.unwrap_or(ConfidenceArg::DEFAULT), strict };
 
-    let results: Vec<_> = args.iter().enumerate().filter_map(|(idx, arg)|{
-        if !used[idx] {
-            f64::from_str(arg).ok().map(|it| {
+    let values: Vec<_> = args.iter().enumerate().filter_map(|(idx, arg)|{
+        // an unknown option could look like a number once units are stripped
+        if !used[idx] && !arg.starts_with("--") {
+            numbers.parse_number(arg).ok().inspect(|_| {
                 used[idx] = true;
-                it
             })
         } else {
             None
         }
-    }).map(|it| (format!("{:.3} km", it), format!("{:.2} $", estimate_price(it, theta)))).collect();
+    }).collect();
+    // each prediction takes one value per feature in model order, each inverse one a price then the other features
+    let chunks = values.chunks_exact(model.features.len());
+    if !chunks.remainder().is_empty() {
+        let expected = if solved.is_some() { ["price".to_string()].iter().chain(&others).cloned().collect::<Vec<_>>() } else { model.features.clone() };
+        println!("Error: {} values given but the model takes {} per prediction ({}), ignoring the last {}", values.len(), model.features.len(), expected.join(", "), chunks.remainder().len());
+    }
+    let results: Vec<_> = chunks.map(|it| query.answer(&model, it)).collect();
+    let mut refused = false;
 
     for (idx, it) in args.iter().enumerate() {
         if !used[idx] {
@@ -31,7 +176,15 @@ fn main() {
     }
 
     if results.is_empty() {
-        println!("Please type in a float kilometer value, or exit to exit");
+        match solved {
+            Some(solved) if others.is_empty() => println!("Please type in a float price to find its {}, or exit to exit", model.features[solved]),
+            Some(solved) => println!("Please type in a float price to find its {}, then values for {} separated by ';' or as name=value pairs, or exit to exit", model.features[solved], others.join(", ")),
+            None if model.features.len() == 1 => println!("Please type in a float {} value, or exit to exit", model.features[0]),
+            None => println!("Please type in float values for {} separated by ';' or as name=value pairs, or exit to exit", model.features.join(", ")),
+        }
+        if model.interval_stats.is_some() {
+            println!("Intervals are at {}%, type level <C> to change it", (query.level * 1e4).round() / 1e2);
+        }
         print!("> ");
         let _ = io::stdout().flush();
         let stdin = io::stdin();
@@ -40,14 +193,31 @@ fn main() {
                 Ok(value) => {
                     if value == "exit" {
                         break
+                    } else if let Some(value) = value.strip_prefix("level ") {
+                        match numbers.parse_number(value) {
+                            Ok(value) if value > 0.0 && value < 1.0 => {
+                                query.level = value;
+                                println!("Intervals are now at {}%", (query.level * 1e4).round() / 1e2)
+                            }
+                            _ => println!("Level must be a float, 0 < C < 1"),
+                        }
                     } else {
-                        match f64::from_str(&value) {
-                            Ok(value) => {
-                                println!("{:.3} km is priced {:.2} $", value, estimate_price(value, theta))
+                        let values = match (solved, value.split_once(';')) {
+                            (None, _) => parse_features(&model.features, &value, &numbers),
+                            (Some(_), None) if others.is_empty() => numbers.parse_number(&value).map(|it| vec![it]),
+                            (Some(_), None) => Err(format!("Expected {} after the price", others.join(", "))),
+                            (Some(_), Some((price, rest))) => {
+                                numbers.parse_number(price)
+                                    .and_then(|price| Ok([price].iter().copied().chain(parse_features(&others, rest, &numbers)?).collect()))
                             }
-                            Err(_) => {
-                                println!("Value must be <float> or exit")
+                        };
+                        match values.map(|values| query.answer(&model, &values)) {
+                            Ok(Ok((left, right, details))) => println!("{} {} {}{}", left, query.verb(), right, details),
+                            Ok(Err(err)) => {
+                                refused = true;
+                                println!("{}", err)
                             }
+                            Err(err) => println!("{}", err),
                         }
                     }
                 }
@@ -59,13 +229,22 @@ fn main() {
             let _ = io::stdout().flush();
         }
     } else {
-        let max = results.iter().fold((0usize, 0usize), |(a, b), (sa, sb)| {
-            (max(a, sa.len()), max(b, sb.len()))
+        let max = results.iter().fold((0usize, 0usize), |(a, b), result| match result {
+            Ok((left, right, _)) => (max(a, left.len()), max(b, right.len())),
+            Err(_) => (a, b),
         });
 
-        for (kilometer, price) in results {
-            println!("{0:>1$} is priced {2:>3$}", kilometer, max.0, price, max.1)
+        for result in results {
+            match result {
+                Ok((left, right, details)) => println!("{0:>1$} {4} {2:>3$}{5}", left, max.0, right, max.1, query.verb(), details),
+                Err(err) => {
+                    refused = true;
+                    println!("Error: {}", err)
+                }
+            }
         }
     }
-
-}
\ No newline at end of file
+    if refused {
+        process::exit(1);
+    }
+}
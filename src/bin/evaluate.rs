@@ -0,0 +1,73 @@
+use std::{env, process};
+use std::path::Path;
+use ft_linear_regression::args::{ArgParser, arg_err};
+use ft_linear_regression::dataset::{DatasetArg, Dataset, ReadOptions};
+use ft_linear_regression::export::Export;
+use ft_linear_regression::metrics::{MetricsExportArg, Metrics, table};
+use ft_linear_regression::theta::{ThetaFileArg, load_theta, DEFAULT_PATH};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).map(|it| it.to_lowercase()).collect();
+    let mut used = vec![false; args.len()];
+    let theta_path = ThetaFileArg::try_parse(&args, &mut used);
+    let dataset_path = DatasetArg::try_parse(&args, &mut used);
+    let read_options = ReadOptions::parse(&args, &mut used);
+    let export_path = MetricsExportArg::try_parse(&args, &mut used);
+
+    for (idx, it) in args.iter().enumerate() {
+        if !used[idx] {
+            arg_err(idx, it, "Arg is not recognized, ignoring. --help for more info");
+        }
+    }
+
+    // scores of the zero model a missing file would fall back to are meaningless
+    let model = match load_theta(theta_path.unwrap_or_else(|| Path::new(DEFAULT_PATH))) {
+        Ok(model) => model,
+        Err(err) => {
+            println!("Error: {}", err);
+            process::exit(1);
+        }
+    };
+    if model.partial {
+        println!("Warning: This model's training was interrupted, scores may be off");
+    }
+    let dataset = match Dataset::read_from(dataset_path, &read_options) {
+        Ok((dataset, report)) => {
+            if !report.skipped.is_empty() {
+                println!("Warning: {}", report);
+            }
+            dataset
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            process::exit(1);
+        }
+    };
+    if let Err(err) = model.check_features(&dataset.features) {
+        println!("Error: The dataset does not fit the model: {}", err);
+        process::exit(1);
+    }
+    if dataset.entries.is_empty() {
+        println!("Error: The dataset has no rows to evaluate the model on");
+        process::exit(1);
+    }
+
+    let metrics = Metrics::of(&model, &dataset);
+    // scores saved at training time, side by side for comparison
+    let mut columns = vec![];
+    if let Some(evaluation) = &model.evaluation {
+        columns.push(("saved train", &evaluation.train));
+        if let Some(test) = &evaluation.test {
+            columns.push(("saved test", test));
+        }
+    }
+    columns.push(("dataset", &metrics));
+    println!("{}", table(&columns));
+    if let Some(path) = export_path {
+        if let Err(err) = metrics.export(path) {
+            println!("Error: {}", err);
+            process::exit(1);
+        }
+        println!("Scores exported to {}", path.display());
+    }
+}
@@ -0,0 +1,20 @@
+use std::{env, process};
+use ft_linear_regression::args::{arg_err, ArgParser};
+use ft_linear_regression::theta::{migrate_theta, ThetaFileArg};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).map(|it| it.to_lowercase()).collect();
+    let mut used = vec![false; args.len()];
+    let theta_path = ThetaFileArg::try_parse(&args, &mut used);
+
+    for (idx, it) in args.iter().enumerate() {
+        if !used[idx] {
+            arg_err(idx, it, "Arg is not recognized, ignoring. --help for more info");
+        }
+    }
+
+    if let Err(err) = migrate_theta(theta_path) {
+        println!("Error: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,195 @@
+use std::{env, process, thread};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ft_linear_regression::args::{ArgParser, DefaultArgParser, arg_err};
+use ft_linear_regression::dataset::{DatasetArg, Dataset, ReadOptions};
+use ft_linear_regression::scaler::ScalerArg;
+use ft_linear_regression::solver::{LearnRatioArg, SolverArg, BatchSizeArg, Solver, GdParams, Batching, fit};
+use ft_linear_regression::penalty::Penalty;
+use ft_linear_regression::random::{SeedArg, Random, seed_from_time};
+use ft_linear_regression::optimizer::OptimizerKind;
+use ft_linear_regression::schedule::Schedule;
+use ft_linear_regression::convergence::{StopCriteria, StopReason};
+use ft_linear_regression::metrics::{SplitSeedArg, Metrics, Evaluation, table};
+use ft_linear_regression::validation::{FoldsArg, cross_validate, mean_std};
+use ft_linear_regression::search::{SearchArg, TrialsArg, ThreadsArg, SearchSeedArg, RatiosArg, LambdasArg, L1RatiosArg, BatchSizesArg, SearchKind, Values, Space, parallel_map};
+use ft_linear_regression::range::TrainingRange;
+use ft_linear_regression::inference::IntervalStats;
+use ft_linear_regression::theta::{ThetaFileArg, save_theta};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).map(|it| it.to_lowercase()).collect();
+    let mut used = vec![false; args.len()];
+    let theta_path = ThetaFileArg::try_parse(&args, &mut used);
+    let dataset_path = DatasetArg::try_parse(&args, &mut used);
+    let read_options = ReadOptions::parse(&args, &mut used);
+    let ratio = LearnRatioArg::parse(&args, &mut used);
+    let scaler_kind = ScalerArg::parse(&args, &mut used);
+    let solver = SolverArg::parse(&args, &mut used);
+    let criteria = StopCriteria::parse_capped(&args, &mut used);
+    let batch_size = BatchSizeArg::try_parse(&args, &mut used);
+    let seed = SeedArg::try_parse(&args, &mut used);
+    let optimizer = OptimizerKind::parse(&args, &mut used);
+    let schedule = Schedule::parse(&args, &mut used);
+    let penalty = Penalty::parse(&args, &mut used);
+    let folds = FoldsArg::parse(&args, &mut used);
+    let split_seed = SplitSeedArg::try_parse(&args, &mut used);
+    let search = SearchArg::parse(&args, &mut used);
+    let trials = TrialsArg::try_parse(&args, &mut used);
+    let threads = ThreadsArg::try_parse(&args, &mut used);
+    let search_seed = SearchSeedArg::try_parse(&args, &mut used);
+    let ratios = RatiosArg::try_parse(&args, &mut used);
+    let lambdas = LambdasArg::try_parse(&args, &mut used);
+    let l1_ratios = L1RatiosArg::try_parse(&args, &mut used);
+    let batch_sizes = BatchSizesArg::try_parse(&args, &mut used);
+
+    // invalid values are all reported before giving up
+    let mut invalid = false;
+    if batch_size == Some(0) {
+        println!("Error: Batch size must be at least 1");
+        invalid = true;
+    }
+    if solver == Solver::Ols && penalty.l1() != 0.0 {
+        println!("Error: The closed form only handles ridge, use --solver cd or gd for {}", penalty);
+        invalid = true;
+    }
+    if trials == Some(0) {
+        println!("Error: Trials must be at least 1");
+        invalid = true;
+    }
+    if threads == Some(0) {
+        println!("Error: Threads must be at least 1");
+        invalid = true;
+    }
+    if solver != Solver::GradientDescent && (ratios.is_some() || batch_sizes.is_some()) {
+        println!("Warning: Only gradient descent has a learning ratio and batches, ignoring --ratios and --batch-sizes");
+    }
+    if penalty == Penalty::None && lambdas.is_some() {
+        println!("Warning: Lambdas need a --penalty, ignoring");
+    }
+    if !matches!(penalty, Penalty::ElasticNet { .. }) && l1_ratios.is_some() {
+        println!("Warning: L1 ratios only apply to --penalty=elasticnet, ignoring");
+    }
+    if search == SearchKind::Grid && (trials.is_some() || search_seed.is_some()) {
+        println!("Warning: Trials and search seed are only used by --search=random, ignoring");
+    }
+    let gradient = solver == Solver::GradientDescent;
+    let space = Space {
+        ratios: ratios.filter(|_| gradient).unwrap_or_else(|| Values::List(vec![ratio])),
+        lambdas: lambdas.filter(|_| penalty != Penalty::None),
+        l1_ratios: l1_ratios.filter(|_| matches!(penalty, Penalty::ElasticNet { .. })),
+        batch_sizes: batch_sizes.filter(|_| gradient),
+    };
+    // ratios, lambdas and batch sizes are spread on a log scale
+    if !space.ratios.positive() || !space.lambdas.as_ref().is_none_or(Values::positive) || !space.batch_sizes.as_ref().is_none_or(Values::positive) {
+        println!("Error: Ratios, lambdas and batch sizes must be positive");
+        invalid = true;
+    }
+    let batch_seed = seed.map_or_else(seed_from_time, |it| it as u64);
+    let batching = batch_size.map(|batch_size| Batching { batch_size, seed: batch_seed });
+    let base = GdParams { ratio, criteria, optimizer, schedule, penalty, batching, checkpoint_every: None, verbose: false };
+
+    for (idx, it) in args.iter().enumerate() {
+        if !used[idx] {
+            arg_err(idx, it, "Arg is not recognized, ignoring. --help for more info");
+        }
+    }
+    if invalid {
+        process::exit(1);
+    }
+
+    let dataset = match Dataset::read_from(dataset_path, &read_options) {
+        Ok((dataset, report)) => {
+            if !report.skipped.is_empty() {
+                println!("Warning: {}", report);
+            }
+            dataset
+        }
+        Err(err) => {
+            println!("Error: {}", err);
+            process::exit(1);
+        }
+    };
+    let candidates = match search {
+        SearchKind::Grid => space.grid(),
+        SearchKind::Random => {
+            let search_seed = search_seed.map_or_else(seed_from_time, |it| it as u64);
+            println!("Random search seed {}", search_seed);
+            space.random(trials.unwrap_or(TrialsArg::DEFAULT), &mut Random::new(search_seed))
+        }
+    };
+    let threads = threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, |it| it.get()));
+    // every candidate gets the same folds, so they are compared on the same rows
+    let split_seed = split_seed.map_or_else(seed_from_time, |it| it as u64);
+    println!("Evaluating {} candidates with {} folds on {} threads, split seed {}", candidates.len(), folds, threads, split_seed);
+
+    // metrics of models the iteration cap stopped are of coefficients that had not converged yet
+    let capped = AtomicUsize::new(0);
+    let fits = AtomicUsize::new(0);
+    let train = |rows: &Dataset, params: &GdParams| fit(rows, scaler_kind, solver, params).inspect(|model| {
+        fits.fetch_add(1, Ordering::Relaxed);
+        if model.training.as_ref().and_then(|it| it.stop) == Some(StopReason::MaxIterations) {
+            capped.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    let results = parallel_map(&candidates, threads, |candidate| {
+        let params = candidate.apply(&base, batch_seed);
+        cross_validate(&dataset, folds, 1, split_seed, |rows| train(rows, &params)).map(|folds| {
+            let test: Vec<_> = folds.iter().map(|it| it.test).collect();
+            let train: Vec<_> = folds.iter().map(|it| it.train).collect();
+            (mean_std(&test), mean_std(&train).0)
+        })
+    });
+    let mut ranked: Vec<_> = candidates.iter().zip(results).collect();
+    // failed candidates last, the others by mean test RMSE
+    ranked.sort_by(|(_, a), (_, b)| match (a, b) {
+        (Ok(((a, _), _)), Ok(((b, _), _))) => a.rmse.total_cmp(&b.rmse),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+    });
+
+    let dash = |it: Option<String>| it.unwrap_or_else(|| "-".into());
+    println!("{:>4} | {:>10} | {:>10} | {:>8} | {:>5} | {:>14} | {:>12} | {:>10} | {:>14}", "rank", "ratio", "lambda", "l1 ratio", "batch", "test RMSE", "std", "test R²", "train RMSE");
+    for (rank, (candidate, result)) in ranked.iter().enumerate() {
+        let params = format!("{:>4} | {:>10.3e} | {:>10} | {:>8} | {:>5}", rank + 1, candidate.ratio,
+            dash(candidate.lambda.map(|it| format!("{:.3e}", it))), dash(candidate.l1_ratio.map(|it| format!("{:.3}", it))), dash(candidate.batch_size.map(|it| it.to_string())));
+        match result {
+            Ok(((mean, std), train)) => println!("{} | {:>14.6} | {:>12.6} | {:>10.6} | {:>14.6}", params, mean.rmse, std.rmse, mean.r2, train.rmse),
+            Err(err) => println!("{} | failed: {}", params, err),
+        }
+    }
+
+    let best = match ranked.first() {
+        Some((candidate, Ok(_))) => **candidate,
+        _ => {
+            println!("Error: Every candidate failed, no model was saved");
+            process::exit(1);
+        }
+    };
+    println!("Best is {}, training it on every row", best);
+    let result = train(&dataset, &best.apply(&base, batch_seed));
+    let capped = capped.into_inner();
+    if capped != 0 {
+        println!("Warning: {} of {} models stopped after {} iterations without converging, raise --max-iterations",
+            capped, fits.into_inner(), base.criteria.max_iterations.unwrap_or_default());
+    }
+    match result {
+        Ok(mut model) => {
+            let train = Metrics::of(&model, &dataset);
+            println!("{}", table(&[("train", &train)]));
+            model.evaluation = Some(Evaluation { train, test: None });
+            model.range = TrainingRange::compute(&dataset);
+            match IntervalStats::compute(&model, &dataset) {
+                Ok(interval_stats) => model.interval_stats = Some(interval_stats),
+                Err(err) => println!("Warning: Predictions will have no intervals: {}", err),
+            }
+            if save_theta(theta_path, &model).is_err() {
+                process::exit(1);
+            }
+        }
+        Err(err) => {
+            println!("Error: Could not train the best candidate: {}", err);
+            process::exit(1);
+        }
+    }
+}
@@ -1,79 +1,352 @@
 use std::env;
-use ft_linear_regression::theta::{ThetaFileArg, save_theta};
-use ft_linear_regression::args::{F64Parser, ArgParser, DefaultArgParser, arg_err};
-use ft_linear_regression::dataset::{DatasetArg, Dataset, DatasetEntry};
-use ft_linear_regression::estimate_price::estimate_price;
+use ft_linear_regression::theta::{ThetaFileArg, save_theta, save_checkpoint, load_theta, Model};
+use ft_linear_regression::args::{ArgParser, DefaultArgParser, arg_err};
+use ft_linear_regression::dataset::{DatasetArg, Dataset, ReadOptions};
+use ft_linear_regression::scaler::{ScalerArg, Scaler};
+use ft_linear_regression::solver::{LearnRatioArg, SolverArg, BatchSizeArg, Solver, TrainingSummary, GdParams, Batching, Start, ols, coordinate_descent, gradient_descent, cost};
+use ft_linear_regression::penalty::Penalty;
+use ft_linear_regression::history::{HistoryArg, LossCurveArg, HistoryStrideArg, History};
+use ft_linear_regression::export::Export;
+use ft_linear_regression::checkpoint::{WarmStartArg, ResumeArg, CheckpointArg, CheckpointEveryArg, Checkpoint};
+use ft_linear_regression::random::{SeedArg, seed_from_time};
+use ft_linear_regression::optimizer::OptimizerKind;
+use ft_linear_regression::schedule::{Schedule, FindRatioArg, lr_range_test, recommend_ratio};
+use ft_linear_regression::convergence::{StopCriteria, StopReason};
+use ft_linear_regression::interrupt;
+use ft_linear_regression::metrics::{TestFractionArg, SplitSeedArg, Split, Metrics, Evaluation, table};
+use ft_linear_regression::range::TrainingRange;
+use ft_linear_regression::inference::{InferenceArg, ConfidenceArg, Inference, IntervalStats};
 use std::time::Instant;
-
-pub struct LearnRatioArg;
-
-impl F64Parser<'_> for LearnRatioArg {
-    const NAMES: &'static [&'static str] = &["-r", "--ratio"];
-    const DESCRIPTION: &'static str = "The Learning Ratio";
-}
-
-impl DefaultArgParser<'_, f64> for LearnRatioArg {
-    const DEFAULT: f64 = 0.00001;
-}
+use std::path::Path;
+use std::process;
 
 fn main() {
     let args: Vec<_> = env::args().skip(1).map(|it| it.to_lowercase()).collect();
     let mut used = vec![false; args.len()];
     let theta_path = ThetaFileArg::try_parse(&args, &mut used);
     let dataset_path = DatasetArg::try_parse(&args, &mut used);
+    let read_options = ReadOptions::parse(&args, &mut used);
     let ratio = LearnRatioArg::parse(&args, &mut used);
+    let scaler_kind = ScalerArg::parse(&args, &mut used);
+    let solver = SolverArg::parse(&args, &mut used);
+    let criteria = StopCriteria::parse(&args, &mut used);
+    let batch_size = BatchSizeArg::try_parse(&args, &mut used);
+    let seed = SeedArg::try_parse(&args, &mut used);
+    let optimizer = OptimizerKind::parse(&args, &mut used);
+    let schedule = Schedule::parse(&args, &mut used);
+    let find_ratio = FindRatioArg::parse(&args, &mut used);
+    let penalty = Penalty::parse(&args, &mut used);
+    let history_path = HistoryArg::try_parse(&args, &mut used);
+    let loss_curve_path = LossCurveArg::try_parse(&args, &mut used);
+    let history_stride = HistoryStrideArg::try_parse(&args, &mut used);
+    let warm_start = WarmStartArg::try_parse(&args, &mut used);
+    let resume = ResumeArg::try_parse(&args, &mut used);
+    let checkpoint_path = CheckpointArg::try_parse(&args, &mut used);
+    let checkpoint_every = CheckpointEveryArg::try_parse(&args, &mut used);
+    let test_fraction = TestFractionArg::try_parse(&args, &mut used);
+    let split_seed = SplitSeedArg::try_parse(&args, &mut used);
+    let inference = InferenceArg::parse(&args, &mut used);
+    let confidence = ConfidenceArg::try_parse(&args, &mut used);
 
-    if ratio >= 1.0 || ratio <= 0.0 {
+    // invalid values are all reported before giving up
+    let mut invalid = false;
+    if solver == Solver::GradientDescent && (ratio >= 1.0 || ratio <= 0.0) {
         println!("Error: Learning ratio must be 0 < R < 1");
+        invalid = true;
+    }
+
+    if batch_size == Some(0) {
+        println!("Error: Batch size must be at least 1");
+        invalid = true;
+    }
+    if confidence.is_some_and(|it| it <= 0.0 || it >= 1.0) {
+        println!("Error: Confidence must be 0 < C < 1");
+        invalid = true;
+    }
+    if confidence.is_some() && !inference {
+        println!("Warning: Confidence is only used with --inference, ignoring");
+    }
+    if seed.is_some() && batch_size.is_none() {
+        println!("Warning: Seed is only used with --batch-size, ignoring");
+    }
+    if checkpoint_every == Some(0) {
+        println!("Error: Checkpoint interval must be at least 1");
+        invalid = true;
+    }
+    if checkpoint_every.is_some() && checkpoint_path.is_none() {
+        println!("Warning: Checkpoint interval is only used with --checkpoint, ignoring");
+    }
+    if solver != Solver::GradientDescent && (warm_start.is_some() || resume.is_some() || checkpoint_path.is_some()) {
+        println!("Warning: Warm starts, checkpoints and resuming only apply to gradient descent, ignoring");
+    }
+    if warm_start.is_some() && resume.is_some() {
+        println!("Warning: A resumed run continues from its checkpoint, ignoring --warm-start");
+    }
+    // the ratio is part of the run being resumed
+    let find_ratio = if find_ratio && resume.is_some() && solver == Solver::GradientDescent {
+        println!("Warning: A resumed run keeps its ratio, ignoring --find-ratio");
+        false
+    } else {
+        find_ratio
+    };
+    let resumed = match resume {
+        Some(path) if solver == Solver::GradientDescent => match load_theta(path) {
+            Ok(model) if model.checkpoint.is_some() && model.scaler.is_some() => Some(model),
+            Ok(_) => {
+                println!("Error: {} is not a checkpoint", path.display());
+                process::exit(1);
+            }
+            Err(err) => {
+                println!("Error: {}", err);
+                process::exit(1);
+            }
+        },
+        _ => None,
+    };
+    // a resumed mini-batch run keeps its batching unless told otherwise
+    let saved_batching = resumed.as_ref().and_then(|it| it.batching);
+    let batching = match batch_size {
+        Some(batch_size) => Some(Batching {
+            batch_size: batch_size.max(1),
+            seed: seed.map(|it| it as u64).or(saved_batching.map(|it| it.seed)).unwrap_or_else(seed_from_time),
+        }),
+        None => saved_batching,
+    };
+    if let Schedule::Backtracking { .. } = schedule {
+        if batching.is_some() {
+            println!("Warning: Backtracking needs the full batch, the ratio will stay constant");
+        }
+        if optimizer != OptimizerKind::Vanilla {
+            println!("Warning: Backtracking searches along the plain gradient, the optimizer may not decrease the cost as expected");
+        }
+    }
+    if test_fraction.is_some_and(|it| !(0.0..1.0).contains(&it)) {
+        println!("Error: Test fraction must be 0 <= F < 1");
+        invalid = true;
+    }
+    if split_seed.is_some() && test_fraction.is_none() {
+        println!("Warning: Split seed is only used with --test-fraction, ignoring");
+    }
+    // a resumed run holds out the same rows unless told otherwise
+    let split = match test_fraction {
+        Some(test_fraction) if test_fraction > 0.0 => Some(Split {
+            test_fraction: test_fraction.min(1.0),
+            seed: split_seed.map(|it| it as u64).or(resumed.as_ref().and_then(|it| it.split).map(|it| it.seed)).unwrap_or_else(seed_from_time),
+        }),
+        Some(_) => None,
+        None => resumed.as_ref().and_then(|it| it.split),
+    };
+    if solver == Solver::Ols && penalty.l1() != 0.0 {
+        println!("Error: The closed form only handles ridge, use --solver cd or gd for {}", penalty);
+        invalid = true;
+    }
+    if history_stride == Some(0) {
+        println!("Error: History stride must be at least 1");
+        invalid = true;
     }
+    let recording = history_path.is_some() || loss_curve_path.is_some();
+    if history_stride.is_some() && !recording {
+        println!("Warning: History stride is only used with --history or --loss-curve, ignoring");
+    }
+    if recording && solver == Solver::Ols {
+        println!("Warning: The closed form has no cost history, nothing will be exported");
+    }
+    let mut history = History::new(if recording { history_stride.unwrap_or(1).max(1) } else { 0 });
+    let checkpoint_every = checkpoint_path.map(|_| checkpoint_every.unwrap_or(CheckpointEveryArg::DEFAULT).max(1));
+    let mut params = GdParams { ratio, criteria, optimizer, schedule, penalty, batching, checkpoint_every, verbose: true };
 
     for (idx, it) in args.iter().enumerate() {
         if !used[idx] {
             arg_err(idx, it, "Arg is not recognized, ignoring. --help for more info");
         }
     }
+    if invalid {
+        process::exit(1);
+    }
 
-    if let Ok(raw) = Dataset::read_from(dataset_path, None) {
-        let mut theta = (0.0, 0.0);
-
-        let normalized = raw.clone().normalize();
-
-        let base = ratio / normalized.entries.len() as f64;
+    // from here on Ctrl-C ends training early and still saves the model
+    interrupt::install();
+    let read = Dataset::read_from(dataset_path, &read_options).map_err(|err| println!("Error: {}", err));
+    if let Ok((raw, report)) = read {
+        println!("{}", report);
+        if let Some(Err(err)) = resumed.as_ref().map(|it| it.check_features(&raw.features)) {
+            println!("Error: Cannot resume: {}", err);
+            process::exit(1);
+        }
+        // the scaler only sees training rows, held out rows must not leak into the model
+        let (raw, test) = match split {
+            Some(split) => {
+                let (train, test) = split.apply(&raw);
+                println!("Holding out {} of {} rows for testing, split seed {}", test.entries.len(), raw.entries.len(), split.seed);
+                (train, Some(test))
+            }
+            None => (raw, None),
+        };
+        // a resumed run keeps the scale its coefficients are in
+        let scaler = match &resumed {
+            Some(Model { scaler: Some(scaler), .. }) => scaler.clone(),
+            _ => Scaler::fit(scaler_kind, &raw),
+        };
+        let normalized = scaler.transform(&raw);
 
-        let mut iter: usize = 0;
-        let start = Instant::now();
-        loop {
-            let tmp = normalized.entries.iter().map(|DatasetEntry { km, price }| {
-                // use the temporary t0 so we don't compute things twice
-                let t0 = estimate_price(*km, theta) - price;
-                (t0, t0 * km)
-            }).reduce(|a, b| (a.0 + b.0, a.1 + b.1)).unwrap_or((0.0, 0.0));
-            let tmp = (tmp.0 * base, tmp.1 * base);
-            let last = theta;
-            theta = (
-                last.0 - tmp.0,
-                last.1 - tmp.1,
-            );
-            iter += 1;
-            if (last.0 - theta.0).abs() == 0.0 && (last.1 - theta.1).abs() == 0.0 {
-                break;
+        let (theta, training) = match solver {
+            Solver::Ols => {
+                let start = Instant::now();
+                match ols(&normalized, penalty) {
+                    Ok(theta) => {
+                        let seconds = start.elapsed().as_secs_f64();
+                        println!("Solved least squares in {:.3}s", seconds);
+                        (theta, TrainingSummary { solver, iterations: 0, seconds, stop: None })
+                    }
+                    Err(err) => {
+                        println!("Error: Could not solve least squares: {}", err);
+                        process::exit(1);
+                    }
+                }
+            }
+            Solver::CoordinateDescent => coordinate_descent(&normalized, penalty, &params.criteria, true, &mut history),
+            Solver::GradientDescent => {
+                let start = if let Some(Model { theta, checkpoint: Some(checkpoint), .. }) = resumed {
+                    if let Err(err) = checkpoint.check(&normalized, &params) {
+                        println!("Error: Cannot resume: {}", err);
+                        process::exit(1);
+                    }
+                    println!("Resuming after {} iterations", checkpoint.iterations);
+                    Start::Resume(theta, checkpoint)
+                } else if let Some(path) = warm_start {
+                    let model = match load_theta(path) {
+                        Ok(model) => model,
+                        Err(err) => {
+                            println!("Error: {}", err);
+                            process::exit(1);
+                        }
+                    };
+                    if let Err(err) = model.check_features(&normalized.features) {
+                        println!("Error: Cannot warm start: {}", err);
+                        process::exit(1);
+                    }
+                    // the model may have been scaled differently, go through raw coefficients
+                    Start::Theta(scaler.transform_theta(&model.raw_theta()))
+                } else {
+                    Start::Zero
+                };
+                if find_ratio {
+                    let sweep = lr_range_test(&normalized, &params, 1e-6, 10.0, 4, 100);
+                    println!("{:>12} | cost after 100 iterations", "ratio");
+                    for (ratio, cost) in &sweep {
+                        println!("{:>12.3e} | {:e}", ratio, cost);
+                    }
+                    match recommend_ratio(&sweep) {
+                        Some(ratio) => {
+                            println!("Recommended ratio is {:e}, training with it", ratio);
+                            params.ratio = ratio;
+                        }
+                        None => println!("Warning: Every ratio diverged, training with {:e}", params.ratio),
+                    }
+                }
+                let mut on_checkpoint = |theta: &[f64], checkpoint: Checkpoint| {
+                    if let Some(path) = checkpoint_path {
+                        let training = TrainingSummary { solver, iterations: checkpoint.iterations, seconds: checkpoint.seconds, stop: None };
+                        let model = Model { theta: theta.to_vec(), features: normalized.features.clone(), scaler: Some(scaler.clone()), training: Some(training), batching, penalty, checkpoint: Some(checkpoint), split, ..Model::default() };
+                        if let Err(err) = save_checkpoint(path, &model) {
+                            println!("Error: {}", err);
+                        }
+                    }
+                };
+                let (theta, training) = gradient_descent(&normalized, &params, start, &mut history, &mut on_checkpoint);
+                if training.stop == Some(StopReason::Diverged) {
+                    println!("Error: Gradient descent diverged, the model was not saved");
+                    let _ = export_history(&history, history_path, loss_curve_path);
+                    process::exit(1);
+                }
+                // whoever pressed Ctrl-C wants out, not a comparison
+                if training.stop != Some(StopReason::Interrupted) {
+                    // lasso has no closed form, a long coordinate descent gets as close as it can
+                    let exact = if penalty.l1() == 0.0 {
+                        ols(&normalized, penalty)
+                    } else {
+                        Ok(coordinate_descent(&normalized, penalty, &StopCriteria { max_iterations: Some(100_000), ..StopCriteria::default() }, false, &mut History::default()).0)
+                    };
+                    match exact {
+                        Ok(exact) => {
+                            let distance = theta.iter().zip(&exact).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt();
+                            let objective = |theta: &[f64]| cost(&normalized, theta) / 2.0 + penalty.value(theta);
+                            println!("Distance to the exact optimum is {:e} in normalized space, objective is {:e} over the optimal {:e}", distance, objective(&theta) - objective(&exact), objective(&exact));
+                        }
+                        Err(err) => println!("Warning: Could not compare with the exact optimum: {}", err),
+                    }
+                }
+                (theta, training)
             }
-            // Cold function because we want the loop to be tight, thus not have all this garbage inlined. Reduces runtime by about 10%
-            #[cold]
-            fn print_info(iter: usize, start: &Instant, theta: (f64, f64)) {
-                println!("{} iterations in {:.3}s", iter, start.elapsed().as_secs_f64());
-                println!("Theta is currently {:?}", theta);
+        };
+
+        let partial = training.stop == Some(StopReason::Interrupted);
+        let mut model = Model { theta, features: normalized.features.clone(), scaler: Some(scaler), training: Some(training), batching: batching.filter(|_| solver == Solver::GradientDescent), penalty, partial, split, ..Model::default() };
+        let evaluation = Evaluation { train: Metrics::of(&model, &raw), test: test.as_ref().map(|it| Metrics::of(&model, it)) };
+        match &evaluation.test {
+            Some(test) => println!("{}", table(&[("train", &evaluation.train), ("test", test)])),
+            None => println!("{}", table(&[("train", &evaluation.train)])),
+        }
+        model.evaluation = Some(evaluation);
+        model.range = TrainingRange::compute(&raw);
+        match IntervalStats::compute(&model, &raw) {
+            Ok(interval_stats) => model.interval_stats = Some(interval_stats),
+            Err(err) => println!("Warning: Predictions will have no intervals: {}", err),
+        }
+        if inference {
+            if model.penalty != Penalty::None {
+                println!("Warning: The statistics assume an unpenalized fit, {} coefficients are biased towards 0", model.penalty);
             }
-            if iter & 0b1111111111111111111111111 == 0 {
-                print_info(iter, &start, theta);
+            match Inference::compute(&model, &raw, confidence.filter(|it| *it > 0.0 && *it < 1.0).unwrap_or(ConfidenceArg::DEFAULT)) {
+                Ok(inference) => println!("{}", inference),
+                Err(err) => println!("Warning: Could not compute the statistics: {}", err),
             }
         }
+        //normalized.draw_to_file_with_theta("normalized.png", &model.theta);
+        if finish(theta_path, model, &history, history_path, loss_curve_path).is_err() {
+            process::exit(1);
+        }
+    } else {
+        process::exit(1);
+    }
+}
 
-        println!("Done {} iterations in {:.3}s", iter, start.elapsed().as_secs_f64());
-        println!("Theta is {:?}", theta);
-        //normalized.draw_to_file_with_theta("normalized.png", theta);
-        //raw.draw_to_file_with_theta("raw.png", raw.denormalize_theta(theta));
+/// Prints the coefficients, saves the model and exports the history, errors when any of it could not be written
+fn finish(theta_path: Option<&Path>, model: Model, history: &History, history_path: Option<&Path>, loss_curve: Option<&Path>) -> Result<(), ()> {
+    if model.penalty != Penalty::None {
+        println!("Penalty is {}, {} of {} coefficients are exactly 0", model.penalty, model.theta[1..].iter().filter(|it| **it == 0.0).count(), model.theta.len() - 1);
+    }
+    println!("Theta is {:?}, {:?} on raw values", model.theta, model.raw_theta());
+    if model.partial {
+        println!("Warning: Training was interrupted, the model is saved as partially trained");
+    }
+    let mut result = Ok(());
+    if let Err(err) = save_theta(theta_path, &model) {
+        println!("Error: {}", err);
+        result = Err(());
+    }
+    if model.training.as_ref().is_some_and(|it| it.solver != Solver::Ols) {
+        result = result.and(export_history(history, history_path, loss_curve));
+    }
+    result
+}
 
-        let _ = save_theta(theta_path, raw.denormalize_theta(theta));
+fn export_history(history: &History, path: Option<&Path>, loss_curve: Option<&Path>) -> Result<(), ()> {
+    let mut result = Ok(());
+    if let Some(path) = path {
+        match history.export(path) {
+            Ok(()) => println!("Saved {} history points to {}", history.points.len(), path.display()),
+            Err(err) => {
+                println!("Error: {}", err);
+                result = Err(());
+            }
+        }
+    }
+    if let Some(path) = loss_curve {
+        if let Err(err) = history.draw_to_file(path) {
+            println!("Error: Could not draw the loss curve: {}", err);
+            result = Err(());
+        }
     }
+    result
 }
\ No newline at end of file
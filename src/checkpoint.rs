@@ -0,0 +1,205 @@
+use crate::args::{FileParser, UsizeParser, DefaultArgParser};
+use crate::codec::{put_u8, put_u32, put_u64, put_f64, ByteReader};
+use crate::dataset::Dataset;
+use crate::optimizer::OptimizerKind;
+use crate::penalty::Penalty;
+use crate::schedule::Schedule;
+use crate::solver::GdParams;
+
+pub struct WarmStartArg;
+
+impl FileParser<'_> for WarmStartArg {
+    const NAMES: &'static [&'static str] = &["--warm-start"];
+    const DESCRIPTION: &'static str = "Starts gradient descent from the coefficients of an existing model file";
+}
+
+pub struct CheckpointArg;
+
+impl FileParser<'_> for CheckpointArg {
+    const NAMES: &'static [&'static str] = &["--checkpoint"];
+    const DESCRIPTION: &'static str = "Saves the model and the training state to this file periodically and when training stops";
+}
+
+pub struct CheckpointEveryArg;
+
+impl UsizeParser<'_> for CheckpointEveryArg {
+    const NAMES: &'static [&'static str] = &["--checkpoint-every"];
+    const DESCRIPTION: &'static str = "Iterations, or epochs in mini-batch mode, between two checkpoints";
+}
+
+impl DefaultArgParser<'_, usize> for CheckpointEveryArg {
+    const DEFAULT: usize = 100000;
+}
+
+pub struct ResumeArg;
+
+impl FileParser<'_> for ResumeArg {
+    const NAMES: &'static [&'static str] = &["--resume"];
+    const DESCRIPTION: &'static str = "Continues the run saved in a checkpoint, given the same training options";
+}
+
+/// Where gradient descent is at, beyond theta. Saved between the coefficients of the model so it can be resumed exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    /// Iterations, or epochs in mini-batch mode, done so far
+    pub iterations: usize,
+    pub seconds: f64,
+    /// Cost of the last iteration, the cost tolerance compares with it
+    pub last_cost: f64,
+    /// Options the run was started with, resuming with others would continue a different run
+    pub ratio: f64,
+    pub optimizer: OptimizerKind,
+    pub schedule: Schedule,
+    pub penalty: Penalty,
+    pub optimizer_state: Vec<f64>,
+    /// Random state and row order of a mini-batch run
+    pub shuffle: Option<(u64, Vec<usize>)>,
+}
+
+impl Checkpoint {
+    /// Checks the checkpoint can continue with these options on this dataset
+    pub fn check(&self, dataset: &Dataset, params: &GdParams) -> Result<(), String> {
+        if self.ratio.to_bits() != params.ratio.to_bits() {
+            return Err(format!("checkpoint was made with ratio {}, got {}", self.ratio, params.ratio));
+        }
+        if self.optimizer != params.optimizer {
+            return Err(format!("checkpoint was made with optimizer {:?}, got {:?}", self.optimizer, params.optimizer));
+        }
+        if self.schedule != params.schedule {
+            return Err(format!("checkpoint was made with schedule {:?}, got {:?}", self.schedule, params.schedule));
+        }
+        if self.penalty != params.penalty {
+            return Err(format!("checkpoint was made with penalty {}, got {}", self.penalty, params.penalty));
+        }
+        if self.optimizer_state.len() != params.optimizer.build(dataset.features.len() + 1).state().len() {
+            return Err("optimizer state does not match the feature count".into());
+        }
+        match (&self.shuffle, params.batching) {
+            (None, None) => Ok(()),
+            (Some((_, order)), Some(_)) if order.len() == dataset.entries.len() => Ok(()),
+            (Some((_, order)), Some(_)) => Err(format!("checkpoint shuffles {} rows, dataset has {}", order.len(), dataset.entries.len())),
+            (Some(_), None) => Err("checkpoint was made in mini-batch mode, --batch-size is missing".into()),
+            (None, Some(_)) => Err("checkpoint was made with the full batch, --batch-size is not allowed".into()),
+        }
+    }
+
+    /// Section payload: iterations as u64, seconds, last cost and ratio as f64, the optimizer, schedule and penalty
+    /// as they encode themselves, u32 state length and the state as f64, then u8 1 followed by the random state as u64,
+    /// u32 row count and each row as u64 for mini-batch runs, u8 0 otherwise
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        put_u64(out, self.iterations as u64);
+        put_f64(out, self.seconds);
+        put_f64(out, self.last_cost);
+        put_f64(out, self.ratio);
+        self.optimizer.encode(out);
+        self.schedule.encode(out);
+        self.penalty.encode(out);
+        put_u32(out, self.optimizer_state.len() as u32);
+        self.optimizer_state.iter().for_each(|it| put_f64(out, *it));
+        match &self.shuffle {
+            Some((random, order)) => {
+                put_u8(out, 1);
+                put_u64(out, *random);
+                put_u32(out, order.len() as u32);
+                order.iter().for_each(|it| put_u64(out, *it as u64));
+            }
+            None => put_u8(out, 0),
+        }
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        let iterations = reader.u64("checkpoint iterations")? as usize;
+        let seconds = reader.f64("checkpoint time")?;
+        let last_cost = reader.f64("checkpoint cost")?;
+        let ratio = reader.f64("checkpoint ratio")?;
+        let optimizer = OptimizerKind::decode(reader)?;
+        let schedule = Schedule::decode(reader)?;
+        let penalty = Penalty::decode(reader)?;
+        let len = reader.u32("optimizer state length")?;
+        let optimizer_state = (0..len).map(|_| reader.f64("optimizer state")).collect::<Result<_, _>>()?;
+        let shuffle = match reader.u8("checkpoint shuffle flag")? {
+            0 => None,
+            1 => {
+                let random = reader.u64("random state")?;
+                let len = reader.u32("row count")?;
+                let order: Vec<usize> = (0..len).map(|_| reader.u64("row order").map(|it| it as usize)).collect::<Result<_, _>>()?;
+                let mut seen = vec![false; order.len()];
+                for &row in &order {
+                    match seen.get_mut(row) {
+                        Some(seen) if !*seen => *seen = true,
+                        Some(_) => return Err(format!("row {} appears twice in the row order", row)),
+                        None => return Err(format!("row {} of the row order is out of range", row)),
+                    }
+                }
+                Some((random, order))
+            }
+            flag => return Err(format!("invalid shuffle flag {}", flag)),
+        };
+        Ok(Self { iterations, seconds, last_cost, ratio, optimizer, schedule, penalty, optimizer_state, shuffle })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convergence::StopCriteria;
+    use crate::dataset::DatasetEntry;
+
+    fn dataset() -> Dataset {
+        Dataset {
+            features: vec!["km".into()],
+            entries: (0..3).map(|idx| DatasetEntry { features: vec![idx as f64], price: idx as f64 }).collect(),
+        }
+    }
+
+    fn params() -> GdParams {
+        GdParams {
+            ratio: 0.1,
+            criteria: StopCriteria::default(),
+            optimizer: OptimizerKind::Momentum { momentum: 0.9 },
+            schedule: Schedule::Constant,
+            penalty: Penalty::None,
+            batching: None,
+            checkpoint_every: None,
+            verbose: false,
+        }
+    }
+
+    fn checkpoint(shuffle: Option<(u64, Vec<usize>)>) -> Checkpoint {
+        let params = params();
+        Checkpoint {
+            iterations: 10,
+            seconds: 0.5,
+            last_cost: 1.5,
+            ratio: params.ratio,
+            optimizer: params.optimizer,
+            schedule: params.schedule,
+            penalty: params.penalty,
+            optimizer_state: vec![0.25, -0.5],
+            shuffle,
+        }
+    }
+
+    fn decode(checkpoint: &Checkpoint) -> Result<Checkpoint, String> {
+        let mut bytes = vec![];
+        checkpoint.encode(&mut bytes);
+        Checkpoint::decode(&mut ByteReader::new(&bytes))
+    }
+
+    #[test]
+    fn checks_the_options() {
+        let (dataset, checkpoint) = (dataset(), checkpoint(None));
+        assert_eq!(checkpoint.check(&dataset, &params()), Ok(()));
+        assert!(checkpoint.check(&dataset, &GdParams { ratio: 0.2, ..params() }).is_err());
+        assert!(checkpoint.check(&dataset, &GdParams { optimizer: OptimizerKind::Nesterov { momentum: 0.9 }, ..params() }).is_err());
+        assert!(checkpoint.check(&dataset, &GdParams { penalty: Penalty::Ridge { lambda: 0.1 }, ..params() }).is_err());
+    }
+
+    #[test]
+    fn row_order_is_a_permutation() {
+        let valid = checkpoint(Some((7, vec![2, 0, 1])));
+        assert_eq!(decode(&valid), Ok(valid));
+        assert!(decode(&checkpoint(Some((7, vec![2, 0, 0])))).is_err());
+        assert!(decode(&checkpoint(Some((7, vec![2, 0, 3])))).is_err());
+    }
+}
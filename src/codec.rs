@@ -0,0 +1,136 @@
+use std::convert::TryInto;
+
+pub(crate) fn put_u8(out: &mut Vec<u8>, value: u8) {
+    out.push(value)
+}
+
+pub(crate) fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_be_bytes())
+}
+
+pub(crate) fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_be_bytes())
+}
+
+pub(crate) fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_be_bytes())
+}
+
+pub(crate) fn put_f64(out: &mut Vec<u8>, value: f64) {
+    out.extend_from_slice(&value.to_be_bytes())
+}
+
+/// Length prefixed UTF-8: u32 byte count then the bytes
+pub(crate) fn put_str(out: &mut Vec<u8>, value: &str) {
+    put_u32(out, value.len() as u32);
+    out.extend_from_slice(value.as_bytes())
+}
+
+/// CRC-32 (IEEE 802.3), bitwise: model files are small enough that a lookup table is not worth it
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+/// Sequential big endian reader, errors describe what was being read when the input ran out
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    /// Bytes left to read, to bound what a count read from the input can allocate
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.pos)
+    }
+
+    pub(crate) fn take(&mut self, len: usize, what: &str) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.pos < len {
+            return Err(format!("unexpected end of data at byte {} while reading {}", self.pos, what));
+        }
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    pub(crate) fn u8(&mut self, what: &str) -> Result<u8, String> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    pub(crate) fn u16(&mut self, what: &str) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.take(2, what)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u32(&mut self, what: &str) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4, what)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self, what: &str) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.take(8, what)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn f64(&mut self, what: &str) -> Result<f64, String> {
+        Ok(f64::from_be_bytes(self.take(8, what)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn str(&mut self, what: &str) -> Result<String, String> {
+        let len = self.u32(what)? as usize;
+        String::from_utf8(self.take(len, what)?.to_vec()).map_err(|err| format!("{} is not valid UTF-8: {}", what, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut out = vec![];
+        put_u16(&mut out, 0xBEEF);
+        put_u32(&mut out, 123_456);
+        put_f64(&mut out, -0.1);
+        let mut reader = ByteReader::new(&out);
+        assert_eq!(reader.u16("u16"), Ok(0xBEEF));
+        assert_eq!(reader.u32("u32"), Ok(123_456));
+        assert_eq!(reader.remaining(), 8);
+        assert_eq!(reader.f64("f64"), Ok(-0.1));
+        assert!(reader.is_empty());
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn big_endian() {
+        let mut out = vec![];
+        put_u32(&mut out, 0x0102_0304);
+        assert_eq!(out, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn truncated() {
+        let mut reader = ByteReader::new(&[0, 1, 2]);
+        assert_eq!(reader.u32("count"), Err("unexpected end of data at byte 0 while reading count".into()));
+        assert_eq!(reader.u16("version"), Ok(1));
+        assert!(reader.u16("kind").unwrap_err().contains("at byte 2 while reading kind"));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::args::{F64Parser, UsizeParser, ArgParser, DefaultArgParser};
+use std::fmt::{self, Display, Formatter};
+use std::time::Instant;
+use crate::interrupt::interrupted;
+
+pub struct ToleranceArg;
+
+impl F64Parser<'_> for ToleranceArg {
+    const NAMES: &'static [&'static str] = &["--tolerance"];
+    const DESCRIPTION: &'static str = "Stop when no coefficient changes by more than this, 0 waits until theta stops changing";
+}
+
+impl DefaultArgParser<'_, f64> for ToleranceArg {
+    const DEFAULT: f64 = 0.0;
+}
+
+pub struct RelToleranceArg;
+
+impl F64Parser<'_> for RelToleranceArg {
+    const NAMES: &'static [&'static str] = &["--rel-tolerance"];
+    const DESCRIPTION: &'static str = "Stop when the largest coefficient change is below this fraction of the largest coefficient";
+}
+
+pub struct CostToleranceArg;
+
+impl F64Parser<'_> for CostToleranceArg {
+    const NAMES: &'static [&'static str] = &["--cost-tolerance"];
+    const DESCRIPTION: &'static str = "Stop when the cost changes by less than this between two iterations";
+}
+
+pub struct GradientToleranceArg;
+
+impl F64Parser<'_> for GradientToleranceArg {
+    const NAMES: &'static [&'static str] = &["--gradient-tolerance"];
+    const DESCRIPTION: &'static str = "Stop when the norm of the gradient falls below this";
+}
+
+pub struct MaxIterationsArg;
+
+impl UsizeParser<'_> for MaxIterationsArg {
+    const NAMES: &'static [&'static str] = &["--max-iterations"];
+    const DESCRIPTION: &'static str = "Stop after this many iterations";
+}
+
+pub struct MaxSecondsArg;
+
+impl F64Parser<'_> for MaxSecondsArg {
+    const NAMES: &'static [&'static str] = &["--max-seconds"];
+    const DESCRIPTION: &'static str = "Stop after training for this many seconds";
+}
+
+/// Default iteration cap of cross-validation and search
+pub const CAPPED_MAX_ITERATIONS: usize = 1_000_000;
+
+/// When gradient descent stops, the first rule that holds wins
+#[derive(Clone, Debug, PartialEq)]
+pub struct StopCriteria {
+    pub tolerance: f64,
+    pub rel_tolerance: Option<f64>,
+    pub cost_tolerance: Option<f64>,
+    pub gradient_tolerance: Option<f64>,
+    pub max_iterations: Option<usize>,
+    pub max_seconds: Option<f64>,
+}
+
+impl Default for StopCriteria {
+    /// Waits until theta stops changing, like training always did
+    fn default() -> Self {
+        Self {
+            tolerance: ToleranceArg::DEFAULT,
+            rel_tolerance: None,
+            cost_tolerance: None,
+            gradient_tolerance: None,
+            max_iterations: None,
+            max_seconds: None,
+        }
+    }
+}
+
+impl StopCriteria {
+    pub fn parse(input: &[String], used: &mut [bool]) -> Self {
+        Self {
+            tolerance: ToleranceArg::parse(input, used),
+            rel_tolerance: RelToleranceArg::try_parse(input, used),
+            cost_tolerance: CostToleranceArg::try_parse(input, used),
+            gradient_tolerance: GradientToleranceArg::try_parse(input, used),
+            max_iterations: MaxIterationsArg::try_parse(input, used),
+            max_seconds: MaxSecondsArg::try_parse(input, used),
+        }
+    }
+
+    /// Like [StopCriteria::parse], but stops after [CAPPED_MAX_ITERATIONS] unless told otherwise.
+    /// For tools training many models, where waiting for theta to stop changing would look like a hang.
+    pub fn parse_capped(input: &[String], used: &mut [bool]) -> Self {
+        let mut criteria = Self::parse(input, used);
+        criteria.max_iterations.get_or_insert(CAPPED_MAX_ITERATIONS);
+        criteria
+    }
+
+    /// Checks every rule but the time budget after an update from `last` to `theta`, and whether training was interrupted
+    pub fn check(&self, iteration: usize, last: &[f64], theta: &[f64], cost_change: f64, gradient_norm: f64) -> Option<StopReason> {
+        if theta.iter().any(|it| !it.is_finite()) {
+            return Some(StopReason::Diverged);
+        }
+        if interrupted() {
+            return Some(StopReason::Interrupted);
+        }
+        let change = last.iter().zip(theta).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max);
+        if change <= self.tolerance {
+            return Some(StopReason::Tolerance);
+        }
+        if let Some(tolerance) = self.rel_tolerance {
+            if change <= tolerance * theta.iter().map(|it| it.abs()).fold(0.0, f64::max) {
+                return Some(StopReason::RelTolerance);
+            }
+        }
+        if let Some(tolerance) = self.cost_tolerance {
+            if cost_change <= tolerance {
+                return Some(StopReason::CostTolerance);
+            }
+        }
+        if let Some(tolerance) = self.gradient_tolerance {
+            if gradient_norm <= tolerance {
+                return Some(StopReason::GradientTolerance);
+            }
+        }
+        if let Some(max) = self.max_iterations {
+            if iteration >= max {
+                return Some(StopReason::MaxIterations);
+            }
+        }
+        None
+    }
+
+    /// Reading the clock costs more than the rest of the checks, so callers decide how often to check the time budget
+    pub fn check_time(&self, start: &Instant) -> Option<StopReason> {
+        match self.max_seconds {
+            Some(max) if start.elapsed().as_secs_f64() >= max => Some(StopReason::MaxSeconds),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StopReason {
+    Tolerance,
+    RelTolerance,
+    CostTolerance,
+    GradientTolerance,
+    MaxIterations,
+    MaxSeconds,
+    /// A coefficient became infinite or NaN, the ratio is too large
+    Diverged,
+    /// SIGINT or SIGTERM was received
+    Interrupted,
+}
+
+impl StopReason {
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            StopReason::Tolerance => 0,
+            StopReason::RelTolerance => 1,
+            StopReason::CostTolerance => 2,
+            StopReason::GradientTolerance => 3,
+            StopReason::MaxIterations => 4,
+            StopReason::MaxSeconds => 5,
+            StopReason::Diverged => 6,
+            StopReason::Interrupted => 7,
+        }
+    }
+
+    pub(crate) fn from_id(id: u8) -> Result<Self, String> {
+        match id {
+            0 => Ok(StopReason::Tolerance),
+            1 => Ok(StopReason::RelTolerance),
+            2 => Ok(StopReason::CostTolerance),
+            3 => Ok(StopReason::GradientTolerance),
+            4 => Ok(StopReason::MaxIterations),
+            5 => Ok(StopReason::MaxSeconds),
+            6 => Ok(StopReason::Diverged),
+            7 => Ok(StopReason::Interrupted),
+            _ => Err(format!("unknown stop reason {}", id)),
+        }
+    }
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StopReason::Tolerance => "coefficient change within tolerance",
+            StopReason::RelTolerance => "relative coefficient change within tolerance",
+            StopReason::CostTolerance => "cost change within tolerance",
+            StopReason::GradientTolerance => "gradient norm within tolerance",
+            StopReason::MaxIterations => "maximum iteration count reached",
+            StopReason::MaxSeconds => "time budget exhausted",
+            StopReason::Diverged => "diverged, try a smaller ratio",
+            StopReason::Interrupted => "interrupt",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(criteria: &StopCriteria, iteration: usize, theta: &[f64], cost_change: f64, gradient_norm: f64) -> Option<StopReason> {
+        criteria.check(iteration, &[1.0, 2.0], theta, cost_change, gradient_norm)
+    }
+
+    #[test]
+    fn default_waits_for_a_fixed_point() {
+        let criteria = StopCriteria::default();
+        assert_eq!(check(&criteria, 1, &[1.0, 2.0 + 1e-12], 0.0, 0.0), None);
+        assert_eq!(check(&criteria, 1, &[1.0, 2.0], 0.0, 0.0), Some(StopReason::Tolerance));
+    }
+
+    #[test]
+    fn each_rule_stops() {
+        let theta = [1.5, 2.0];
+        let base = StopCriteria::default();
+        assert_eq!(check(&StopCriteria { rel_tolerance: Some(0.3), ..base.clone() }, 1, &theta, 1.0, 1.0), Some(StopReason::RelTolerance));
+        assert_eq!(check(&StopCriteria { rel_tolerance: Some(0.2), ..base.clone() }, 1, &theta, 1.0, 1.0), None);
+        assert_eq!(check(&StopCriteria { cost_tolerance: Some(0.5), ..base.clone() }, 1, &theta, 0.25, 1.0), Some(StopReason::CostTolerance));
+        assert_eq!(check(&StopCriteria { gradient_tolerance: Some(0.5), ..base.clone() }, 1, &theta, 1.0, 0.25), Some(StopReason::GradientTolerance));
+        assert_eq!(check(&StopCriteria { max_iterations: Some(3), ..base.clone() }, 2, &theta, 1.0, 1.0), None);
+        assert_eq!(check(&StopCriteria { max_iterations: Some(3), ..base }, 3, &theta, 1.0, 1.0), Some(StopReason::MaxIterations));
+    }
+
+    #[test]
+    fn divergence_wins() {
+        let criteria = StopCriteria { max_iterations: Some(1), ..StopCriteria::default() };
+        assert_eq!(check(&criteria, 1, &[f64::NAN, 2.0], 0.0, 0.0), Some(StopReason::Diverged));
+        assert_eq!(check(&criteria, 1, &[1.0, f64::INFINITY], 0.0, 0.0), Some(StopReason::Diverged));
+    }
+
+    #[test]
+    fn stop_reason_ids_round_trip() {
+        for id in 0..8 {
+            assert_eq!(StopReason::from_id(id).map(StopReason::id), Ok(id));
+        }
+        assert!(StopReason::from_id(8).is_err());
+    }
+
+    #[test]
+    fn capped_parse_keeps_a_given_cap() {
+        let parse = |args: &[&str]| {
+            let args: Vec<_> = args.iter().map(|it| it.to_string()).collect();
+            StopCriteria::parse_capped(&args, &mut vec![false; args.len()]).max_iterations
+        };
+        assert_eq!(parse(&[]), Some(CAPPED_MAX_ITERATIONS));
+        assert_eq!(parse(&["--max-iterations=42"]), Some(42));
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt::{self, Display, Formatter};
+
+/// Lines starting with this, after any whitespace, are skipped
+pub const COMMENT: char = '#';
+
+/// One record of a CSV file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// Line the record starts on, 1 based. Quoted fields can span lines, so records and lines do not always match.
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+/// Why a text is not valid CSV
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvError {
+    pub line: usize,
+    /// 0 based, of the field at fault
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for CsvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Splits `text` into records, following RFC 4180 with a few leniencies: any `separator`, LF or CRLF line ends,
+/// a leading UTF-8 BOM, whitespace around fields, blank lines and [COMMENT] lines. Quoted fields may hold
+/// separators, line breaks and quotes doubled as `""`, whitespace inside quotes is kept.
+pub fn read_records(text: &str, separator: &str) -> Result<Vec<Record>, CsvError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let separator = if separator.is_empty() { "," } else { separator };
+    // spaces and tabs around fields are trimmed, unless they separate them
+    let blank = |it: char| (it == ' ' || it == '\t') && !separator.starts_with(it);
+    let mut records = vec![];
+    let (mut pos, mut line) = (0, 1);
+    while pos < text.len() {
+        let rest = &text[pos..];
+        let line_len = rest.find('\n').map_or(rest.len(), |it| it + 1);
+        let trimmed = rest[..line_len].trim();
+        if trimmed.is_empty() || trimmed.starts_with(COMMENT) {
+            pos += line_len;
+            line += 1;
+            continue;
+        }
+        let mut record = Record { line, fields: vec![] };
+        loop {
+            let column = record.fields.len();
+            pos += text[pos..].len() - text[pos..].trim_start_matches(blank).len();
+            let field = if text[pos..].starts_with('"') {
+                let (start_line, mut value) = (line, String::new());
+                pos += 1;
+                loop {
+                    let quote = text[pos..].find('"').ok_or_else(|| CsvError { line: start_line, column, message: "quote is never closed".into() })?;
+                    value.push_str(&text[pos..pos + quote]);
+                    line += text[pos..pos + quote].matches('\n').count();
+                    pos += quote + 1;
+                    if text[pos..].starts_with('"') {
+                        value.push('"');
+                        pos += 1;
+                    } else {
+                        break;
+                    }
+                }
+                pos += text[pos..].len() - text[pos..].trim_start_matches(blank).len();
+                if !(text[pos..].is_empty() || text[pos..].starts_with(separator) || text[pos..].starts_with('\n') || text[pos..].starts_with("\r\n")) {
+                    return Err(CsvError { line, column, message: "unexpected text after a closing quote".into() });
+                }
+                value.replace("\r\n", "\n")
+            } else {
+                let end = text[pos..].char_indices().map(|(idx, _)| pos + idx)
+                    .find(|it| text[*it..].starts_with(separator) || text[*it..].starts_with('\n'))
+                    .unwrap_or(text.len());
+                let value = text[pos..end].trim_end_matches(|it: char| blank(it) || it == '\r').to_string();
+                pos = end;
+                value
+            };
+            record.fields.push(field);
+            if text[pos..].starts_with(separator) {
+                pos += separator.len();
+            } else {
+                // a line end or the end of the text
+                pos += text[pos..].find('\n').map_or(text.len() - pos, |it| it + 1);
+                line += 1;
+                break;
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Separators [detect_separator] chooses from, by preference: a decimal comma is more likely in a file
+/// separated by semicolons than a semicolon in a file separated by commas
+pub const CANDIDATES: [&str; 4] = [";", "\t", "|", ","];
+
+/// Records [detect_separator] looks at
+const SNIFFED_RECORDS: usize = 10;
+
+/// Guesses the separator from the first records of `text`: the preferred of [CANDIDATES] appearing the same number of times
+/// in each record, outside quotes, or else the most frequent one. None when there is none of them.
+pub fn detect_separator(text: &str) -> Option<&'static str> {
+    let mut lines = text.lines().map(str::trim);
+    let mut records = vec![];
+    while let Some(line) = lines.next() {
+        if line.is_empty() || line.starts_with(COMMENT) {
+            continue;
+        }
+        // the text of the record outside quotes, a quoted field holding line breaks carries it over the next lines
+        let (mut outside, mut quoted, mut line) = (String::new(), false, Some(line));
+        while let Some(text) = line {
+            for (idx, part) in text.split('"').enumerate() {
+                if idx != 0 {
+                    quoted = !quoted;
+                }
+                if !quoted {
+                    outside.push_str(part);
+                }
+            }
+            line = if quoted { lines.next() } else { None };
+        }
+        records.push(outside);
+        if records.len() == SNIFFED_RECORDS {
+            break;
+        }
+    }
+    let counts: Vec<Vec<usize>> = CANDIDATES.iter().map(|separator| {
+        records.iter().map(|record| record.matches(separator).count()).collect()
+    }).collect();
+    let consistent = CANDIDATES.iter().zip(&counts).find(|(_, counts)| counts.first().is_some_and(|first| *first > 0 && counts.iter().all(|it| it == first)));
+    match consistent {
+        Some((separator, _)) => Some(separator),
+        None => CANDIDATES.iter().zip(&counts).map(|(separator, counts)| (separator, counts.iter().sum::<usize>()))
+            .filter(|(_, total)| *total > 0).max_by_key(|(_, total)| *total).map(|(separator, _)| *separator),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(text: &str, separator: &str) -> Vec<Vec<String>> {
+        read_records(text, separator).unwrap().into_iter().map(|it| it.fields).collect()
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(fields("km,price\n240000,3650\n", ","), [["km", "price"], ["240000", "3650"]]);
+        assert_eq!(fields("km,price\n240000,3650", ","), [["km", "price"], ["240000", "3650"]]);
+        assert_eq!(fields("km\n", ","), [["km"]]);
+        assert_eq!(fields("a,,b\n", ","), [["a", "", "b"]]);
+    }
+
+    #[test]
+    fn quoted() {
+        assert_eq!(fields("\"a,b\",c\n", ","), [["a,b", "c"]]);
+        assert_eq!(fields("\"say \"\"hi\"\"\",c\n", ","), [["say \"hi\"", "c"]]);
+        assert_eq!(fields("\"\",c\n", ","), [["", "c"]]);
+        assert_eq!(fields("  \" a \"  ,c\n", ","), [[" a ", "c"]]);
+    }
+
+    #[test]
+    fn quoted_line_breaks() {
+        let records = read_records("note,price\n\"two\nlines\",1\nx,2\n", ",").unwrap();
+        assert_eq!(records[1], Record { line: 2, fields: vec!["two\nlines".into(), "1".into()] });
+        assert_eq!(records[2], Record { line: 4, fields: vec!["x".into(), "2".into()] });
+    }
+
+    #[test]
+    fn crlf() {
+        assert_eq!(fields("km,price\r\n240000,3650\r\n", ","), [["km", "price"], ["240000", "3650"]]);
+        assert_eq!(fields("\"a\r\nb\",c\r\n", ","), [["a\nb", "c"]]);
+        assert_eq!(fields("a,\"b\"\r\nc,d", ","), [["a", "b"], ["c", "d"]]);
+    }
+
+    #[test]
+    fn bom() {
+        assert_eq!(fields("\u{feff}km,price\n1,2\n", ","), [["km", "price"], ["1", "2"]]);
+        assert_eq!(fields("\u{feff}\"km\",price\n", ","), [["km", "price"]]);
+    }
+
+    #[test]
+    fn blank_and_comment_lines() {
+        let records = read_records("# cars\nkm,price\n\n  # more\n1,2\n", ",").unwrap();
+        assert_eq!(records.iter().map(|it| it.line).collect::<Vec<_>>(), [2, 5]);
+    }
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(fields(" a ,\tb\t\n", ","), [["a", "b"]]);
+        assert_eq!(fields("a\tb\n", "\t"), [["a", "b"]]);
+        assert_eq!(fields("a\t\tb\n", "\t"), [["a", "", "b"]]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(read_records("a,\"b\nc\n", ","), Err(CsvError { line: 1, column: 1, message: "quote is never closed".into() }));
+        assert_eq!(read_records("a\n\"b\"c,d\n", ","), Err(CsvError { line: 2, column: 0, message: "unexpected text after a closing quote".into() }));
+    }
+
+    #[test]
+    fn detects_separators() {
+        assert_eq!(detect_separator("km,price\n1,2\n"), Some(","));
+        assert_eq!(detect_separator("km;price\n1,5;2,5\n"), Some(";"));
+        assert_eq!(detect_separator("km\tprice\n1\t2\n"), Some("\t"));
+        assert_eq!(detect_separator("km|price\n1|2\n"), Some("|"));
+        assert_eq!(detect_separator("km\n1\n"), None);
+    }
+
+    #[test]
+    fn detects_outside_quotes() {
+        assert_eq!(detect_separator("note,price\n\"a;b;c\",1\n\"d\",2\n"), Some(","));
+        // the semicolons of a quoted field spanning lines are not counted either
+        assert_eq!(detect_separator("note,price\n\"a;\n;b;;;\nc\",1\nd,2\n"), Some(","));
+        assert_eq!(detect_separator("# a;b;c\nkm,price\n1,2\n"), Some(","));
+    }
+}
@@ -1,14 +1,16 @@
-use crate::args::FileParser;
+use crate::args::{ArgParser, BoolParser, DefaultArgParser, F64Parser, FileParser, StringParser};
 use std::path::{Path};
 use std::fs::{OpenOptions};
 use std::io::{Read};
-use std::fmt::Display;
-use std::str::{FromStr, Lines};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
 use plotters::prelude::{BitMapBackend, WHITE, ChartBuilder, IntoFont, LineSeries, RED, PathElement, BLACK, PointSeries, EmptyElement};
 use plotters::drawing::IntoDrawingArea;
 use plotters::style::Color;
 use plotters::element::Circle;
 use crate::estimate_price::estimate_price;
+use crate::csv::{read_records, detect_separator, Record};
+use crate::locale::NumberFormat;
 
 pub struct DatasetArg;
 
@@ -17,142 +19,480 @@ impl FileParser<'_> for DatasetArg {
     const DESCRIPTION: &'static str = "The Learning Dataset, csv formatted with column headers";
 }
 
-#[derive(Copy, Clone)]
+pub struct SeparatorArg;
+
+impl StringParser<'_> for SeparatorArg {
+    const NAMES: &'static [&'static str] = &["--separator"];
+    const DESCRIPTION: &'static str = "Column separator of the dataset, \"tab\" for tabs, guessed among , ; tab and | when not set";
+}
+
+impl SeparatorArg {
+    /// The separator a value of this arg stands for
+    pub fn separator(value: &str) -> &str {
+        match value {
+            "tab" => "\t",
+            value => value,
+        }
+    }
+}
+
+/// Name of the column predicted by the model, every other column is a feature
+pub const TARGET_COLUMN: &str = "price";
+
+/// Built-in header synonyms, a column named like the first is read as the second
+pub const SYNONYMS: &[(&str, &str)] = &[
+    ("mileage", "km"),
+    ("kilometres", "km"),
+    ("kilometers", "km"),
+    ("odometer", "km"),
+    ("sale_price", "price"),
+    ("selling_price", "price"),
+];
+
+/// A column picked by name, or by 0 based index
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl Column {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "" => Err("Column name is empty".into()),
+            value => Ok(usize::from_str(value).map_or_else(|_| Column::Name(value.to_string()), Column::Index)),
+        }
+    }
+}
+
+pub struct FeaturesArg;
+
+impl<'a> ArgParser<'a, Vec<Column>> for FeaturesArg {
+    const NAMES: &'static [&'static str] = &["--features"];
+    const VALUES: &'static [&'static str] = &["<name|index,...>"];
+    const DESCRIPTION: &'static str = "Feature columns, every column but the target by default";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<Vec<Column>, String> {
+        value.ok_or_else(|| "Arg value is not optional, --help for more info".to_string())?.split(',').map(Column::parse).collect()
+    }
+}
+
+pub struct TargetArg;
+
+impl<'a> ArgParser<'a, Column> for TargetArg {
+    const NAMES: &'static [&'static str] = &["--target"];
+    const VALUES: &'static [&'static str] = &["<name>", "<index>"];
+    const DESCRIPTION: &'static str = "Target column, \"price\" by default, the last one without a header";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<Column, String> {
+        Column::parse(value.ok_or_else(|| "Arg value is not optional, --help for more info".to_string())?)
+    }
+}
+
+pub struct SynonymsArg;
+
+impl<'a> ArgParser<'a, Vec<(String, String)>> for SynonymsArg {
+    const NAMES: &'static [&'static str] = &["--synonyms"];
+    const VALUES: &'static [&'static str] = &["<alias:name,...>"];
+    const DESCRIPTION: &'static str = "Reads columns named alias as name, on top of the built-in synonyms";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<Vec<(String, String)>, String> {
+        value.ok_or_else(|| "Arg value is not optional, --help for more info".to_string())?.split(',').map(|it| {
+            match it.split_once(':') {
+                Some((alias, name)) if !alias.trim().is_empty() && !name.trim().is_empty() => Ok((alias.trim().to_string(), name.trim().to_string())),
+                _ => Err(format!("\"{}\" is not an alias:name pair", it)),
+            }
+        }).collect()
+    }
+}
+
+pub struct NoHeaderArg;
+
+impl BoolParser<'_> for NoHeaderArg {
+    const NAMES: &'static [&'static str] = &["--no-header"];
+    const DESCRIPTION: &'static str = "The first row is data, columns are named column0, column1... after their position, --synonyms can rename them";
+}
+
+/// Which columns of a dataset are read, and how they are named
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Columns {
+    /// None for every column but the target
+    pub features: Option<Vec<Column>>,
+    pub target: Option<Column>,
+    /// Checked before [SYNONYMS]
+    pub synonyms: Vec<(String, String)>,
+    pub headerless: bool,
+}
+
+impl Columns {
+    pub fn parse(input: &[String], used: &mut [bool]) -> Self {
+        Self {
+            features: FeaturesArg::try_parse(input, used),
+            target: TargetArg::try_parse(input, used),
+            synonyms: SynonymsArg::try_parse(input, used).unwrap_or_default(),
+            headerless: NoHeaderArg::try_parse(input, used).unwrap_or(false),
+        }
+    }
+
+    /// The name a header is read as, itself when it has no synonym
+    pub fn canonical(&self, name: &str) -> String {
+        self.synonyms.iter().map(|(alias, name)| (alias.as_str(), name.as_str())).chain(SYNONYMS.iter().copied())
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+            .map_or_else(|| name.to_string(), |(_, name)| name.to_string())
+    }
+}
+
+pub struct BadRowsArg;
+
+impl<'a> ArgParser<'a, BadRows> for BadRowsArg {
+    const NAMES: &'static [&'static str] = &["--bad-rows"];
+    const VALUES: &'static [&'static str] = &["skip", "fail"];
+    const DESCRIPTION: &'static str = "Skips rows with a missing or bad value and reports them, or fails on the first one";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<BadRows, String> {
+        match value {
+            Some("skip") => Ok(BadRows::Skip),
+            Some("fail") => Ok(BadRows::Fail),
+            Some(value) => Err(format!("Invalid value \"{}\", must be one of {}", value, Self::VALUES.join(", "))),
+            None => Err("Arg value is not optional, --help for more info".into()),
+        }
+    }
+}
+
+impl DefaultArgParser<'_, BadRows> for BadRowsArg {
+    const DEFAULT: BadRows = BadRows::Skip;
+}
+
+pub struct MaxErrorRateArg;
+
+impl F64Parser<'_> for MaxErrorRateArg {
+    const NAMES: &'static [&'static str] = &["--max-error-rate"];
+    const DESCRIPTION: &'static str = "Fails when more than this fraction of the rows are skipped, 0 <= R <= 1. No limit by default";
+}
+
+/// What to do with a row that has a missing or bad value
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BadRows {
+    Skip,
+    Fail,
+}
+
+/// How a dataset file is read
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadOptions {
+    /// Guessed when None
+    pub separator: Option<String>,
+    pub columns: Columns,
+    pub numbers: NumberFormat,
+    pub bad_rows: BadRows,
+    /// Fraction of skipped rows above which reading fails, None for no limit
+    pub max_error_rate: Option<f64>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self { separator: None, columns: Columns::default(), numbers: NumberFormat::default(), bad_rows: BadRows::Skip, max_error_rate: None }
+    }
+}
+
+impl ReadOptions {
+    pub fn parse(input: &[String], used: &mut [bool]) -> Self {
+        let bad_rows = BadRowsArg::parse(input, used);
+        let mut max_error_rate = MaxErrorRateArg::try_parse(input, used);
+        if max_error_rate.is_some_and(|it| !(0.0..=1.0).contains(&it)) {
+            println!("Error: Max error rate must be 0 <= R <= 1, ignoring");
+            max_error_rate = None;
+        } else if max_error_rate.is_some() && bad_rows == BadRows::Fail {
+            println!("Warning: Max error rate only applies when skipping bad rows, ignoring");
+            max_error_rate = None;
+        }
+        Self {
+            separator: SeparatorArg::try_parse(input, used).map(|it| SeparatorArg::separator(it).to_string()),
+            columns: Columns::parse(input, used),
+            numbers: NumberFormat::parse(input, used),
+            bad_rows,
+            max_error_rate,
+        }
+    }
+}
+
+/// Why a dataset, or one of its rows, could not be read
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorCause {
+    /// The file could not be opened or read
+    Io(String),
+    /// See [CsvError], its line and column are the error's
+    Csv(String),
+    Empty,
+    /// A column asked for is not in the file, or there is no feature or no target column
+    Header(String),
+    /// The row is too short to have a value for the named column
+    MissingValue(String),
+    /// The value of the named column is not a number, and why
+    BadValue(String, String),
+    /// More rows than [ReadOptions::max_error_rate] were skipped, out of `rows`
+    TooManyBadRows { skipped: Vec<DatasetError>, rows: usize, max_rate: f64 },
+    /// No row is left to train on, there were `rows` and all of `skipped` were bad
+    NoUsableRows { skipped: Vec<DatasetError>, rows: usize },
+}
+
+impl Display for ErrorCause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCause::Io(err) => write!(f, "{}", err),
+            ErrorCause::Csv(err) => write!(f, "not valid csv, {}", err),
+            ErrorCause::Empty => write!(f, "file is empty"),
+            ErrorCause::Header(err) => write!(f, "{}", err),
+            ErrorCause::MissingValue(name) => write!(f, "{} value is missing", name),
+            ErrorCause::BadValue(name, err) => write!(f, "bad {} value, {}", name, err),
+            ErrorCause::TooManyBadRows { skipped, rows, max_rate } => {
+                write!(f, "{} of {} rows are bad, more than the max error rate of {}%", skipped.len(), rows, (max_rate * 1e4).round() / 1e2)?;
+                match skipped.first() {
+                    Some(first) => write!(f, ", the first at {}: {}", first.location(), first.cause),
+                    None => Ok(()),
+                }
+            }
+            ErrorCause::NoUsableRows { skipped, rows } => {
+                match skipped.first() {
+                    Some(first) => write!(f, "no usable rows, all {} rows are bad, the first at {}: {}", rows, first.location(), first.cause),
+                    None => write!(f, "no usable rows, there are no data rows"),
+                }
+            }
+        }
+    }
+}
+
+/// An error reading a dataset, located as precisely as it can be
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatasetError {
+    pub file: String,
+    /// 1 based, among data rows
+    pub row: Option<usize>,
+    /// 1 based, of the file
+    pub line: Option<usize>,
+    /// 0 based
+    pub column: Option<usize>,
+    pub cause: ErrorCause,
+}
+
+impl DatasetError {
+    /// An error about the whole file
+    fn new(file: &str, cause: ErrorCause) -> Self {
+        Self { file: file.to_string(), row: None, line: None, column: None, cause }
+    }
+
+    /// Where the error is in the file, "row 3 (line 5), column 1", empty for the whole file
+    pub fn location(&self) -> String {
+        let mut parts = vec![];
+        match (self.row, self.line) {
+            (Some(row), Some(line)) => parts.push(format!("row {} (line {})", row, line)),
+            (Some(row), None) => parts.push(format!("row {}", row)),
+            (None, Some(line)) => parts.push(format!("line {}", line)),
+            (None, None) => {}
+        }
+        if let Some(column) = self.column {
+            parts.push(format!("column {}", column));
+        }
+        parts.join(", ")
+    }
+}
+
+impl Display for DatasetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.location().as_str() {
+            "" => write!(f, "Dataset {}: {}", self.file, self.cause),
+            location => write!(f, "Dataset {}, {}: {}", self.file, location, self.cause),
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+/// Skipped rows [ReadReport] lists one by one
+const LISTED_ROWS: usize = 10;
+
+/// The data rows of a dataset file, and why those skipped were
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadReport {
+    pub file: String,
+    pub rows: usize,
+    pub skipped: Vec<DatasetError>,
+}
+
+impl Display for ReadReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.skipped.is_empty() {
+            return write!(f, "Read {} rows of dataset {}", self.rows, self.file);
+        }
+        write!(f, "Read {} of {} rows of dataset {}, skipped {} ({}%):", self.rows - self.skipped.len(), self.rows, self.file,
+            self.skipped.len(), (self.skipped.len() as f64 / self.rows as f64 * 1e4).round() / 1e2)?;
+        for err in self.skipped.iter().take(LISTED_ROWS) {
+            write!(f, "\n  {}: {}", err.location(), err.cause)?;
+        }
+        if self.skipped.len() > LISTED_ROWS {
+            write!(f, "\n  and {} more", self.skipped.len() - LISTED_ROWS)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
 pub struct DatasetEntry {
-    pub km: f64,
+    /// One value per feature, in the order of [Dataset::features]
+    pub features: Vec<f64>,
     pub price: f64,
 }
 
 #[derive(Clone)]
 pub struct Dataset {
+    /// Feature column names, as written in the header
+    pub features: Vec<String>,
     pub entries: Vec<DatasetEntry>
 }
 
 
 impl Dataset {
-    fn parse_header(headers: &str, separator: Option<&str>, dataset_file: impl Display) -> Result<(usize, usize), ()> {
-        let headers = headers.split(separator.unwrap_or(",")).enumerate().fold((None, None), |(km, price), (idx, header)| {
-            let mut known = false;
-            let km = if header.eq_ignore_ascii_case("km") {
-                known = true;
-                if let Some(km) = km {
-                    println!("Error: Duplicate column #{} in dataset {}: \"km\" column is already defined at index {}", idx, dataset_file, km);
-                    Some(km)
-                } else {
-                    Some(idx)
-                }
-            } else {
-                km
-            };
-            let price = if header.eq_ignore_ascii_case("price") {
-                known = true;
-                if let Some(price) = price {
-                    println!("Error: Duplicate column #{} in dataset {}: \"price\" column is already defined at index {}", idx, dataset_file, price);
-                    Some(price)
-                } else {
-                    Some(idx)
-                }
-            } else {
-                price
-            };
-            if !known {
-                println!("Warning: Unknown column \"{}\" #{} in dataset {}", idx, header, dataset_file);
+    /// Index of `column` among `names`, which are canonical
+    fn find_column(names: &[String], column: &Column, columns: &Columns, dataset_file: &str) -> Result<usize, DatasetError> {
+        match column {
+            Column::Index(idx) if *idx < names.len() => Ok(*idx),
+            Column::Index(idx) => Err(DatasetError::new(dataset_file, ErrorCause::Header(format!("there is no column #{}, the dataset has {}", idx, names.len())))),
+            Column::Name(name) => {
+                let name = columns.canonical(name);
+                names.iter().position(|it| it.eq_ignore_ascii_case(&name))
+                    .ok_or_else(|| DatasetError::new(dataset_file, ErrorCause::Header(format!("column \"{}\" is missing", name))))
             }
-            (km, price)
-        });
-
-        match headers {
-            (Some(km), Some(price)) => Ok((km, price)),
-            (Some(_), None) => Err(println!("Error: Column \"price\" is missing in dataset {}", dataset_file)),
-            (None, Some(_)) => Err(println!("Error: Column \"km\" is missing in dataset {}", dataset_file)),
-            (None, None) => Err(println!("Error: Columns \"km\" and \"price\" are missing in dataset {}", dataset_file)),
         }
     }
 
-    fn parse_lines(lines: Lines, (km, price): (usize, usize), separator: Option<&str>, dataset_file: impl Display) -> Result<Dataset, ()> {
-        let entries = lines.enumerate().filter_map(|(idx, line)| {
-            let idx = idx + 1;
-            if line.is_empty() {
-                return None;
-            }
-            let mut columns = line.split(separator.unwrap_or(","));
-            let (km, price) = if km < price {
-                (
-                    columns.nth(km).and_then(|str| {
-                        f64::from_str(str).map_err(|err| println!("Error: Row {} in dataset {} had bad km value in column {}: {}", idx, dataset_file, km, err)).ok()
-                    }).or_else(|| {
-                        println!("Error: Row {} in dataset {} is missing km value in column {}", idx, dataset_file, km);
-                        None
-                    })?,
-                    columns.nth(price - km - 1).and_then(|str| {
-                        f64::from_str(str).map_err(|err| println!("Error: Row {} in dataset {} had bad price value in column {}: {}", idx, dataset_file, price, err)).ok()
-                    }).or_else(|| {
-                        println!("Error: Row {} in dataset {} is missing price value in column {}", idx, dataset_file, price);
-                        None
-                    })?
-                )
-            } else {
-                (
-                    columns.nth(price).and_then(|str| {
-                        f64::from_str(str).map_err(|err| println!("Error: Row {} in dataset {} had bad price value in column {}: {}", idx, dataset_file, price, err)).ok()
-                    }).or_else(|| {
-                        println!("Error: Row {} in dataset {} is missing price value in column {}", idx, dataset_file, price);
-                        None
-                    })?,
-                    columns.nth(km - price - 1).and_then(|str| {
-                        f64::from_str(str).map_err(|err| println!("Error: Row {} in dataset {} had bad km value in column {}: {}", idx, dataset_file, km, err)).ok()
-                    }).or_else(|| {
-                        println!("Error: Row {} in dataset {} is missing km value in column {}", idx, dataset_file, km);
-                        None
-                    })?
-                )
-            };
-            Some(DatasetEntry {
-                km,
-                price,
-            })
-        }).collect();
-        Ok(Dataset { entries })
+    /// Column `idx` is named `name` like column `first` before it
+    fn duplicate(idx: usize, name: &str, first: usize, dataset_file: &str) -> DatasetError {
+        let cause = ErrorCause::Header(format!("duplicate column, \"{}\" column is already defined at index {}", name, first));
+        DatasetError { column: Some(idx), ..DatasetError::new(dataset_file, cause) }
     }
 
-    pub fn read_from(path: Option<&Path>, separator: Option<&str>) -> Result<Dataset, ()> {
-        let path = path.unwrap_or_else(|| Path::new("./data.csv"));
-        match OpenOptions::new().read(true).open(path) {
-            Ok(mut file) => {
-                let mut string = String::new();
-                file.read_to_string(&mut string).map_err(|err| println!("Error: Could not read dataset file {}: {}", path.display(), err))?;
-                drop(file);
-                let mut lines = string.lines();
-                let headers = lines.next().ok_or_else(|| println!("Error: Dataset file {} is empty", path.display()))?;
-                let headers = Self::parse_header(headers, separator, path.display())?;
-                Self::parse_lines(lines, headers, separator, path.display())
+    /// Returns the index and name of every feature column, and the index of the target column.
+    /// `headers` are the names of the columns, or None without a header, with `width` columns.
+    fn parse_header(headers: Option<&Record>, width: usize, columns: &Columns, dataset_file: &str) -> Result<(Vec<(usize, String)>, usize), DatasetError> {
+        let names: Vec<_> = match headers {
+            Some(headers) => headers.fields.iter().map(|it| columns.canonical(it)).collect(),
+            None => (0..width).map(|idx| columns.canonical(&format!("column{}", idx))).collect(),
+        };
+        let price = match &columns.target {
+            Some(target) => Some(Self::find_column(&names, target, columns, dataset_file)?),
+            None if headers.is_none() => width.checked_sub(1),
+            None => {
+                let mut price: Option<usize> = None;
+                for (idx, header) in names.iter().enumerate() {
+                    if header.eq_ignore_ascii_case(TARGET_COLUMN) {
+                        if let Some(price) = price {
+                            return Err(Self::duplicate(idx, TARGET_COLUMN, price, dataset_file));
+                        }
+                        price = Some(idx);
+                    }
+                }
+                price
             }
-            Err(err) => {
-                Err(println!("Error: Could not open dataset file {} with read permission: {}", path.display(), err))
+        };
+        let picked: Vec<_> = match &columns.features {
+            Some(features) => features.iter().map(|it| Self::find_column(&names, it, columns, dataset_file)).collect::<Result<_, _>>()?,
+            None => (0..names.len()).filter(|idx| Some(*idx) != price && !names[*idx].eq_ignore_ascii_case(TARGET_COLUMN)).collect(),
+        };
+        let mut features: Vec<(usize, String)> = vec![];
+        for idx in picked {
+            let header = &names[idx];
+            if Some(idx) == price {
+                let cause = ErrorCause::Header("the target column cannot be a feature too".to_string());
+                return Err(DatasetError { column: Some(idx), ..DatasetError::new(dataset_file, cause) });
+            } else if let Some((first, _)) = features.iter().find(|(_, name)| name.eq_ignore_ascii_case(header)) {
+                return Err(Self::duplicate(idx, header, *first, dataset_file));
+            } else if header.is_empty() {
+                println!("Warning: Column #{} in dataset {} has no name, ignoring", idx, dataset_file);
+            } else {
+                features.push((idx, header.clone()));
             }
         }
+
+        let err = match (features.is_empty(), price) {
+            (false, Some(price)) => return Ok((features, price)),
+            (false, None) => format!("column \"{}\" is missing", TARGET_COLUMN),
+            (true, Some(_)) => "there is no feature column besides the target".to_string(),
+            (true, None) => format!("there is no feature column and column \"{}\" is missing", TARGET_COLUMN),
+        };
+        Err(DatasetError::new(dataset_file, ErrorCause::Header(err)))
     }
 
-    fn gen_box(&self) -> ((f64, f64), (f64, f64)) {
-        self.entries.iter().fold(((f64::MAX, f64::MIN), (f64::MAX, f64::MIN)), |a, b| ((a.0.0.min(b.km), a.0.1.max(b.km)), (a.1.0.min(b.price), a.1.1.max(b.price))))
+    /// Rows with a missing or bad value fail the read or are skipped, as `options` say. Fails when no row is left.
+    fn parse_records(records: &[Record], (features, price): (Vec<(usize, String)>, usize), options: &ReadOptions, dataset_file: &str) -> Result<(Dataset, ReadReport), DatasetError> {
+        let mut entries = vec![];
+        let mut skipped = vec![];
+        for (idx, record) in records.iter().enumerate() {
+            let error = |column: usize, cause: ErrorCause| DatasetError { file: dataset_file.to_string(), row: Some(idx + 1), line: Some(record.line), column: Some(column), cause };
+            let value = |column: usize, name: &str| {
+                match record.fields.get(column) {
+                    Some(str) => options.numbers.parse_number(str).map_err(|err| error(column, ErrorCause::BadValue(name.to_string(), err))),
+                    None => Err(error(column, ErrorCause::MissingValue(name.to_string()))),
+                }
+            };
+            let entry = features.iter().map(|(column, name)| value(*column, name)).collect::<Result<_, _>>()
+                .and_then(|features| Ok(DatasetEntry { features, price: value(price, TARGET_COLUMN)? }));
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(err) if options.bad_rows == BadRows::Fail => return Err(err),
+                Err(err) => skipped.push(err),
+            }
+        }
+        if let Some(max_rate) = options.max_error_rate {
+            if skipped.len() as f64 > max_rate * records.len() as f64 {
+                return Err(DatasetError::new(dataset_file, ErrorCause::TooManyBadRows { skipped, rows: records.len(), max_rate }));
+            }
+        }
+        if entries.is_empty() {
+            return Err(DatasetError::new(dataset_file, ErrorCause::NoUsableRows { skipped, rows: records.len() }));
+        }
+        let report = ReadReport { file: dataset_file.to_string(), rows: records.len(), skipped };
+        Ok((Dataset { features: features.into_iter().map(|(_, name)| name).collect(), entries }, report))
     }
 
-    pub fn normalize(mut self) -> Self {
-        let ((kmmin, kmmax), (prmin, prmax)) = self.gen_box();
-        self.entries.iter_mut().for_each(|it| {
-            it.km = (it.km - kmmin) / (kmmax - kmmin);
-            it.price = (it.price - prmin) / (prmax - prmin);
-        });
-        self
+    /// Reads a csv dataset, see [read_records] for what it accepts. The first record holds the column names unless the columns are headerless.
+    /// Without a separator, it is guessed by [detect_separator] and reported. The report lists the rows skipped.
+    pub fn read_from(path: Option<&Path>, options: &ReadOptions) -> Result<(Dataset, ReadReport), DatasetError> {
+        let path = path.unwrap_or_else(|| Path::new("./data.csv"));
+        let file_name = path.display().to_string();
+        let mut file = OpenOptions::new().read(true).open(path)
+            .map_err(|err| DatasetError::new(&file_name, ErrorCause::Io(format!("could not be opened with read permission: {}", err))))?;
+        let mut string = String::new();
+        file.read_to_string(&mut string).map_err(|err| DatasetError::new(&file_name, ErrorCause::Io(format!("could not be read: {}", err))))?;
+        drop(file);
+        let separator = match &options.separator {
+            Some(separator) => separator.as_str(),
+            None => {
+                let detected = detect_separator(&string).unwrap_or(",");
+                println!("Dataset {} is separated by {}", file_name, if detected == "\t" { "tabs".to_string() } else { format!("\"{}\"", detected) });
+                detected
+            }
+        };
+        let records = read_records(&string, separator).map_err(|err| DatasetError {
+            line: Some(err.line),
+            column: Some(err.column),
+            ..DatasetError::new(&file_name, ErrorCause::Csv(err.message))
+        })?;
+        let width = records.first().ok_or_else(|| DatasetError::new(&file_name, ErrorCause::Empty))?.fields.len();
+        let (headers, records) = if options.columns.headerless { (None, &records[..]) } else { (Some(&records[0]), &records[1..]) };
+        let headers = Self::parse_header(headers, width, &options.columns, &file_name)?;
+        Self::parse_records(records, headers, options, &file_name)
     }
 
-    pub fn denormalize_theta(&self, theta: (f64, f64)) -> (f64, f64) {
-        let ((kmmin, kmmax), (prmin, prmax)) = self.gen_box();
-        let theta1 = theta.1 / (kmmax - kmmin) * (prmax - prmin);
-        (theta.0 * (prmax - prmin) + prmin - kmmin * theta1, theta1)
+    /// Bounds of the first feature and of the price
+    fn gen_box(&self) -> ((f64, f64), (f64, f64)) {
+        self.entries.iter().fold(((f64::MAX, f64::MIN), (f64::MAX, f64::MIN)), |a, b| ((a.0.0.min(b.features[0]), a.0.1.max(b.features[0])), (a.1.0.min(b.price), a.1.1.max(b.price))))
     }
 
-    pub fn draw_to_file_with_theta(&self, file: &str, theta: (f64, f64)) -> Result<(), Box<dyn std::error::Error>> {
+    /// Only single feature datasets can be drawn
+    pub fn draw_to_file_with_theta(&self, file: &str, theta: &[f64]) -> Result<(), Box<dyn std::error::Error>> {
+        if self.features.len() != 1 {
+            return Err(format!("cannot draw a dataset with {} features, only 1 is supported", self.features.len()).into());
+        }
         let ((kmmin, kmmax), (prmin, prmax)) = self.gen_box();
         let root = BitMapBackend::new(file, (1000, 1000)).into_drawing_area();
 
@@ -168,27 +508,144 @@ impl Dataset {
 
         chart
             .draw_series(PointSeries::of_element(
-                self.entries.iter().map(|it| (it.km, it.price)),
+                self.entries.iter().map(|it| (it.features[0], it.price)),
                 2,
-                &RED,
+                RED,
                 &|c, s, st| {
-                    return EmptyElement::at(c)    // We want to construct a composed element on-the-fly
+                    EmptyElement::at(c)    // We want to construct a composed element on-the-fly
                         + Circle::new((0,0),s,st.filled()) // At this point, the new pixel coordinate is established
                 }
             ))?
             .label("data")
-            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
 
         chart.draw_series(LineSeries::new(
-            vec![(kmmin, estimate_price(kmmin, theta)), (kmmax, estimate_price(kmmax, theta))],
-            &RED,
+            vec![(kmmin, estimate_price(&[kmmin], theta)), (kmmax, estimate_price(&[kmmax], theta))],
+            RED,
         ))?;
 
         chart
             .configure_series_labels()
-            .background_style(&WHITE.mix(0.8))
-            .border_style(&BLACK)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
             .draw()?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(names: &[&str]) -> Record {
+        Record { line: 1, fields: names.iter().map(|it| it.to_string()).collect() }
+    }
+
+    fn names(picked: &(Vec<(usize, String)>, usize)) -> (Vec<&str>, usize) {
+        (picked.0.iter().map(|(_, name)| name.as_str()).collect(), picked.1)
+    }
+
+    #[test]
+    fn every_column_but_the_target() {
+        let picked = Dataset::parse_header(Some(&header(&["km", "Price", "age"])), 3, &Columns::default(), "test").unwrap();
+        assert_eq!(names(&picked), (vec!["km", "age"], 1));
+    }
+
+    #[test]
+    fn picks_by_name_or_index() {
+        let columns = Columns { features: Some(vec![Column::Index(2), Column::Name("KM".into())]), target: Some(Column::Name("cost".into())), ..Columns::default() };
+        let picked = Dataset::parse_header(Some(&header(&["km", "cost", "age", "price"])), 4, &columns, "test").unwrap();
+        assert_eq!(names(&picked), (vec!["age", "km"], 1));
+        let missing = Columns { features: Some(vec![Column::Index(4)]), ..Columns::default() };
+        assert!(Dataset::parse_header(Some(&header(&["km", "price"])), 2, &missing, "test").is_err());
+        assert!(Dataset::parse_header(Some(&header(&["km", "age"])), 2, &Columns::default(), "test").is_err());
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        let cause = |names: &[&str], columns: &Columns| match Dataset::parse_header(Some(&header(names)), names.len(), columns, "test") {
+            Err(DatasetError { column, cause: ErrorCause::Header(_), .. }) => column,
+            other => panic!("expected a header error, got {:?}", other.map(|it| it.1)),
+        };
+        assert_eq!(cause(&["price", "km", "Price"], &Columns::default()), Some(2));
+        assert_eq!(cause(&["km", "price", "KM"], &Columns::default()), Some(2));
+        let target_too = Columns { features: Some(vec![Column::Name("km".into()), Column::Index(1)]), ..Columns::default() };
+        assert_eq!(cause(&["km", "price"], &target_too), Some(1));
+    }
+
+    #[test]
+    fn synonyms() {
+        let columns = Columns { synonyms: vec![("years".into(), "age".into())], ..Columns::default() };
+        assert_eq!(columns.canonical("Mileage"), "km");
+        let picked = Dataset::parse_header(Some(&header(&["odometer", "years", "selling_price"])), 3, &columns, "test").unwrap();
+        assert_eq!(names(&picked), (vec!["km", "age"], 2));
+    }
+
+    #[test]
+    fn headerless_target_is_the_last_column() {
+        let picked = Dataset::parse_header(None, 3, &Columns { headerless: true, ..Columns::default() }, "test").unwrap();
+        assert_eq!(names(&picked), (vec!["column0", "column1"], 2));
+        let columns = Columns { headerless: true, target: Some(Column::Index(0)), synonyms: vec![("column1".into(), "km".into())], ..Columns::default() };
+        let picked = Dataset::parse_header(None, 2, &columns, "test").unwrap();
+        assert_eq!(names(&picked), (vec!["km"], 0));
+    }
+
+    #[test]
+    fn column_values() {
+        assert_eq!(Column::parse(" 3 "), Ok(Column::Index(3)));
+        assert_eq!(Column::parse("km"), Ok(Column::Name("km".into())));
+        assert!(Column::parse(" ").is_err());
+        assert_eq!(SynonymsArg::parse_arg_value(Some("a:b, c : d")), Ok(vec![("a".into(), "b".into()), ("c".into(), "d".into())]));
+        assert!(SynonymsArg::parse_arg_value(Some("a:")).is_err());
+        assert_eq!((SeparatorArg::separator("tab"), SeparatorArg::separator(";")), ("\t", ";"));
+    }
+
+    fn records(rows: &[&[&str]]) -> Vec<Record> {
+        rows.iter().enumerate().map(|(idx, fields)| Record { line: idx + 2, fields: fields.iter().map(|it| it.to_string()).collect() }).collect()
+    }
+
+    #[test]
+    fn bad_rows_are_skipped_or_fail() {
+        let rows = records(&[&["1", "2"], &["x", "3"], &["4"], &["5", "6"]]);
+        let columns = || (vec![(0, "km".to_string())], 1);
+        let (dataset, report) = Dataset::parse_records(&rows, columns(), &ReadOptions::default(), "test").unwrap();
+        assert_eq!(dataset.entries.iter().map(|it| it.price).collect::<Vec<_>>(), [2.0, 6.0]);
+        assert_eq!(report.skipped.len(), 2);
+        assert_eq!((report.skipped[0].row, report.skipped[0].line, report.skipped[0].column), (Some(2), Some(3), Some(0)));
+        assert_eq!(report.skipped[1].cause, ErrorCause::MissingValue("price".into()));
+        assert_eq!(report.skipped[1].to_string(), "Dataset test, row 3 (line 4), column 1: price value is missing");
+
+        let fail = ReadOptions { bad_rows: BadRows::Fail, ..ReadOptions::default() };
+        match Dataset::parse_records(&rows, columns(), &fail, "test") {
+            Err(err) => assert_eq!(err.row, Some(2)),
+            Ok(_) => panic!("read"),
+        }
+
+        let capped = ReadOptions { max_error_rate: Some(0.25), ..ReadOptions::default() };
+        match Dataset::parse_records(&rows, columns(), &capped, "test") {
+            Err(err) => assert!(matches!(err.cause, ErrorCause::TooManyBadRows { rows: 4, .. })),
+            Ok(_) => panic!("read"),
+        }
+        let lenient = ReadOptions { max_error_rate: Some(0.5), ..ReadOptions::default() };
+        assert!(Dataset::parse_records(&rows, columns(), &lenient, "test").is_ok());
+    }
+
+    #[test]
+    fn no_usable_rows() {
+        let columns = || (vec![(0, "km".to_string())], 1);
+        match Dataset::parse_records(&records(&[&["x", "1"], &["2"]]), columns(), &ReadOptions::default(), "test") {
+            Err(err) => assert!(matches!(err.cause, ErrorCause::NoUsableRows { ref skipped, rows: 2 } if skipped.len() == 2)),
+            Ok(_) => panic!("read"),
+        }
+        match Dataset::parse_records(&[], columns(), &ReadOptions::default(), "test") {
+            Err(err) => assert_eq!(err.to_string(), "Dataset test: no usable rows, there are no data rows"),
+            Ok(_) => panic!("read"),
+        }
+    }
+
+    #[test]
+    fn file_errors_have_no_location() {
+        let err = DatasetError::new("data.csv", ErrorCause::Empty);
+        assert_eq!(err.location(), "");
+        assert_eq!(err.to_string(), "Dataset data.csv: file is empty");
+    }
+}
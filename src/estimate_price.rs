@@ -1,5 +1,50 @@
+use crate::args::{BoolParser, StringParser};
 
+pub struct InverseArg;
+
+impl BoolParser<'_> for InverseArg {
+    const NAMES: &'static [&'static str] = &["--inverse"];
+    const DESCRIPTION: &'static str = "Takes prices and finds the feature value giving them, other features follow each price";
+}
+
+pub struct SolveForArg;
+
+impl StringParser<'_> for SolveForArg {
+    const NAMES: &'static [&'static str] = &["--solve-for"];
+    const DESCRIPTION: &'static str = "Feature found by --inverse, the first one by default";
+}
+
+/// theta0 plus the dot product of the features with the remaining coefficients
 #[inline]
-pub const fn estimate_price(kilometers: f64, theta: (f64, f64)) -> f64 {
-    theta.0 + (theta.1 * kilometers)
-}
\ No newline at end of file
+pub fn estimate_price(features: &[f64], theta: &[f64]) -> f64 {
+    theta[0] + features.iter().zip(&theta[1..]).map(|(feature, theta)| theta * feature).sum::<f64>()
+}
+
+/// Solves [estimate_price] for the feature at `solved` so that the price is `price`, the other features
+/// taken from `features`. None when its coefficient is 0: the price does not depend on it.
+pub fn estimate_feature(price: f64, features: &[f64], solved: usize, theta: &[f64]) -> Option<f64> {
+    let slope = theta[solved + 1];
+    if slope == 0.0 {
+        return None;
+    }
+    let others = theta[0] + features.iter().zip(&theta[1..]).enumerate()
+        .filter(|(idx, _)| *idx != solved)
+        .map(|(_, (feature, theta))| theta * feature).sum::<f64>();
+    Some((price - others) / slope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_gives_the_price_back() {
+        let (theta, features) = ([1.0, 2.0, -0.5], [3.0, 4.0]);
+        let price = estimate_price(&features, &theta);
+        assert_eq!(price, 5.0);
+        assert_eq!(estimate_feature(price, &features, 0, &theta), Some(3.0));
+        assert_eq!(estimate_feature(price, &features, 1, &theta), Some(4.0));
+        assert_eq!(estimate_feature(9.0, &features, 0, &theta), Some(5.0));
+        assert_eq!(estimate_feature(price, &features, 1, &[1.0, 2.0, 0.0]), None);
+    }
+}
@@ -0,0 +1,35 @@
+use std::path::Path;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// Results that can be saved as csv or json text
+pub trait Export {
+    fn to_csv(&self) -> String;
+    fn to_json(&self) -> String;
+
+    /// Writes json if the file ends with .json, csv otherwise
+    fn export(&self, path: &Path) -> Result<(), String> {
+        let content = match path.extension().and_then(|it| it.to_str()) {
+            Some("json") => self.to_json(),
+            _ => self.to_csv(),
+        };
+        write(path, &content)
+    }
+}
+
+/// Creates or truncates `path` and writes `content` to it
+pub fn write(path: &Path, content: &str) -> Result<(), String> {
+    let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(path)
+        .map_err(|err| format!("Could not open {}: {}", path.display(), err))?;
+    file.write_all(content.as_bytes()).map_err(|err| format!("Could not write to {}: {}", path.display(), err))
+}
+
+/// A csv cell, NaN is left empty
+pub fn csv_number(value: f64) -> String {
+    if value.is_nan() { String::new() } else { value.to_string() }
+}
+
+/// A json number, non finite values are null
+pub fn json_number(value: f64) -> String {
+    if value.is_finite() { format!("{:e}", value) } else { "null".into() }
+}
@@ -0,0 +1,148 @@
+use crate::args::{FileParser, UsizeParser, DefaultArgParser};
+use crate::export::{Export, csv_number, json_number};
+use std::path::Path;
+use plotters::prelude::{BitMapBackend, WHITE, ChartBuilder, IntoFont, LineSeries, RED, BLUE, PathElement, BLACK, IntoLogRange};
+use plotters::drawing::IntoDrawingArea;
+use plotters::style::Color;
+
+pub struct HistoryArg;
+
+impl FileParser<'_> for HistoryArg {
+    const NAMES: &'static [&'static str] = &["--history"];
+    const DESCRIPTION: &'static str = "Exports the cost history, as json if the file ends with .json, csv otherwise";
+}
+
+pub struct LossCurveArg;
+
+impl FileParser<'_> for LossCurveArg {
+    const NAMES: &'static [&'static str] = &["--loss-curve"];
+    const DESCRIPTION: &'static str = "Draws the cost history to a png file";
+}
+
+pub struct HistoryStrideArg;
+
+impl UsizeParser<'_> for HistoryStrideArg {
+    const NAMES: &'static [&'static str] = &["--history-stride"];
+    const DESCRIPTION: &'static str = "Iterations between two recorded points of the history";
+}
+
+impl DefaultArgParser<'_, usize> for HistoryStrideArg {
+    const DEFAULT: usize = 1;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HistoryPoint {
+    pub iteration: usize,
+    /// Mean squared error, in the scaled space the model is trained in
+    pub cost: f64,
+    /// Norm of the mean gradient, penalty included, NaN for solvers that do not compute it
+    pub gradient_norm: f64,
+}
+
+/// Cost of every `stride`-th iteration, or epoch in mini-batch mode. A stride of 0 records nothing.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History {
+    pub stride: usize,
+    pub points: Vec<HistoryPoint>,
+}
+
+impl History {
+    pub fn new(stride: usize) -> Self {
+        Self { stride, points: vec![] }
+    }
+
+    #[inline]
+    pub fn record(&mut self, iteration: usize, cost: f64, gradient_norm: f64) {
+        if self.stride != 0 && iteration.is_multiple_of(self.stride) {
+            self.points.push(HistoryPoint { iteration, cost, gradient_norm });
+        }
+    }
+
+    /// Cost and gradient norm against iterations, on a log scale
+    pub fn draw_to_file(&self, file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let finite = |it: f64| it.is_finite() && it > 0.0;
+        let (min, max) = self.points.iter().flat_map(|it| [it.cost, it.gradient_norm]).filter(|it| finite(*it))
+            .fold((f64::MAX, f64::MIN), |(min, max), it| (min.min(it), max.max(it)));
+        if min > max {
+            return Err("no positive finite cost to draw".into());
+        }
+        let last = self.points.last().map_or(1, |it| it.iteration.max(1));
+        let root = BitMapBackend::new(file, (1000, 600)).into_drawing_area();
+
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(&root)
+            .caption("Loss Curve", ("sans-serif", 50).into_font())
+            .margin(5)
+            .x_label_area_size(50)
+            .y_label_area_size(80)
+            .build_cartesian_2d(0..last, (min..max).log_scale())?;
+
+        chart.configure_mesh().x_desc("iteration").y_label_formatter(&|it| format!("{:.0e}", it)).draw()?;
+
+        chart
+            .draw_series(LineSeries::new(self.points.iter().filter(|it| finite(it.cost)).map(|it| (it.iteration, it.cost)), RED))?
+            .label("cost")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+        chart
+            .draw_series(LineSeries::new(self.points.iter().filter(|it| finite(it.gradient_norm)).map(|it| (it.iteration, it.gradient_norm)), BLUE))?
+            .label("gradient norm")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+
+        chart
+            .configure_series_labels()
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        Ok(())
+    }
+}
+
+impl Export for History {
+    /// `iteration,cost,gradient_norm` rows, a missing gradient norm is left empty
+    fn to_csv(&self) -> String {
+        let mut content = String::from("iteration,cost,gradient_norm\n");
+        for point in &self.points {
+            content.push_str(&format!("{},{},{}\n", point.iteration, csv_number(point.cost), csv_number(point.gradient_norm)));
+        }
+        content
+    }
+
+    /// An array of `{"iteration", "cost", "gradient_norm"}` objects, non finite values are null
+    fn to_json(&self) -> String {
+        let points: Vec<_> = self.points.iter().map(|point| {
+            format!("  {{\"iteration\": {}, \"cost\": {}, \"gradient_norm\": {}}}", point.iteration, json_number(point.cost), json_number(point.gradient_norm))
+        }).collect();
+        format!("[\n{}\n]\n", points.join(",\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> History {
+        let mut history = History::new(2);
+        history.record(1, 4.0, 2.0);
+        history.record(2, 1.0, 0.5);
+        history.record(3, 0.5, 0.25);
+        history.record(4, 0.25, f64::NAN);
+        history
+    }
+
+    #[test]
+    fn records_every_stride() {
+        assert_eq!(history().points.iter().map(|it| it.iteration).collect::<Vec<_>>(), [2, 4]);
+        let mut disabled = History::default();
+        disabled.record(0, 1.0, 1.0);
+        assert!(disabled.points.is_empty());
+    }
+
+    #[test]
+    fn csv_and_json() {
+        assert_eq!(history().to_csv(), "iteration,cost,gradient_norm\n2,1,0.5\n4,0.25,\n");
+        assert_eq!(
+            history().to_json(),
+            "[\n  {\"iteration\": 2, \"cost\": 1e0, \"gradient_norm\": 5e-1},\n  {\"iteration\": 4, \"cost\": 2.5e-1, \"gradient_norm\": null}\n]\n"
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use crate::args::{BoolParser, F64Parser, DefaultArgParser};
+use crate::codec::{put_u32, put_u64, put_f64, ByteReader};
+use crate::dataset::Dataset;
+use crate::linalg::{Matrix, Qr};
+use crate::theta::Model;
+use std::fmt::{self, Display, Formatter};
+
+pub struct InferenceArg;
+
+impl BoolParser<'_> for InferenceArg {
+    const NAMES: &'static [&'static str] = &["--inference"];
+    const DESCRIPTION: &'static str = "Prints standard errors, t-statistics, p-values and confidence intervals of the coefficients";
+}
+
+pub struct ConfidenceArg;
+
+impl F64Parser<'_> for ConfidenceArg {
+    const NAMES: &'static [&'static str] = &["--confidence"];
+    const DESCRIPTION: &'static str = "Level of the confidence intervals, 0 < C < 1";
+}
+
+impl DefaultArgParser<'_, f64> for ConfidenceArg {
+    const DEFAULT: f64 = 0.95;
+}
+
+/// Natural log of the gamma function, Lanczos approximation (g = 7), accurate to about 15 digits for x > 0
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8, 771.323_428_777_653_1,
+        -176.615_029_162_140_6, 12.507_343_278_686_905, -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6, 1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // reflection, the series only holds on the right half plane
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let sum = COEFFICIENTS[1..].iter().enumerate().fold(COEFFICIENTS[0], |sum, (idx, it)| sum + it / (x + idx as f64 + 1.0));
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Regularized incomplete beta function `I_x(a, b)`, by its continued fraction (modified Lentz)
+pub fn incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    // the fraction converges quickly below the mean, the symmetry covers the rest
+    if x > (a + 1.0) / (a + b + 2.0) {
+        return 1.0 - incomplete_beta(1.0 - x, b, a);
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp() / a;
+    let tiny = 1e-300;
+    let clamp = |it: f64| if it.abs() < tiny { tiny } else { it };
+    let (mut c, mut d) = (1.0, 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0)));
+    let mut fraction = d;
+    for m in 1..=300 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + even * d);
+        c = clamp(1.0 + even / c);
+        fraction *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + odd * d);
+        c = clamp(1.0 + odd / c);
+        let step = d * c;
+        fraction *= step;
+        if (step - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    front * fraction
+}
+
+/// Probability that a Student t variable with `dof` degrees of freedom is further from 0 than `t`
+pub fn t_p_value(t: f64, dof: f64) -> f64 {
+    if t.is_nan() {
+        return f64::NAN;
+    }
+    incomplete_beta(dof / (dof + t * t), dof / 2.0, 0.5)
+}
+
+/// The `t` with `t_p_value(t, dof) = alpha`, the half width of a `1 - alpha` interval in standard errors
+pub fn t_quantile(alpha: f64, dof: f64) -> f64 {
+    let (mut low, mut high) = (0.0, 1.0);
+    while t_p_value(high, dof) > alpha && high < 1e12 {
+        high *= 2.0;
+    }
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if t_p_value(mid, dof) > alpha {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Probability that an F variable with (`dof1`, `dof2`) degrees of freedom exceeds `f`
+pub fn f_p_value(f: f64, dof1: f64, dof2: f64) -> f64 {
+    if f.is_nan() {
+        return f64::NAN;
+    }
+    incomplete_beta(dof2 / (dof2 + dof1 * f), dof2 / 2.0, dof1 / 2.0)
+}
+
+/// Statistics of one raw coefficient
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CoefficientStats {
+    pub estimate: f64,
+    pub std_error: f64,
+    pub t: f64,
+    /// Two-sided, for the hypothesis that the coefficient is 0
+    pub p_value: f64,
+    /// Bounds of the confidence interval
+    pub low: f64,
+    pub high: f64,
+}
+
+/// Least squares inference on the raw coefficients of a model, assuming independent normal errors of constant variance
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inference {
+    /// theta0 then the features
+    pub names: Vec<String>,
+    pub rows: usize,
+    /// Rows minus coefficients
+    pub dof: usize,
+    pub confidence: f64,
+    /// Estimate of the error standard deviation
+    pub residual_std_error: f64,
+    pub coefficients: Vec<CoefficientStats>,
+    /// Whether the features explain anything, against a model with theta0 only
+    pub f_statistic: f64,
+    pub f_p_value: f64,
+}
+
+impl Inference {
+    /// From the residuals of `model` on `dataset`, with `confidence` level intervals.
+    /// Fails without more rows than coefficients, or when the features are collinear.
+    pub fn compute(model: &Model, dataset: &Dataset, confidence: f64) -> Result<Self, String> {
+        let (rows, coefficients) = (dataset.entries.len(), model.features.len() + 1);
+        if rows <= coefficients {
+            return Err(format!("{} rows leave no degree of freedom for {} coefficients", rows, coefficients));
+        }
+        let mut design = Matrix::zeros(rows, coefficients);
+        for (row, entry) in dataset.entries.iter().enumerate() {
+            design.set(row, 0, 1.0);
+            entry.features.iter().enumerate().for_each(|(col, it)| design.set(row, col + 1, *it));
+        }
+        let gram = Qr::new(design)?.gram_inverse();
+
+        let dof = rows - coefficients;
+        let residuals = dataset.entries.iter().map(|it| it.price - model.predict(&it.features)).map(|it| it * it).sum::<f64>();
+        let mean = dataset.entries.iter().map(|it| it.price).sum::<f64>() / rows as f64;
+        let total = dataset.entries.iter().map(|it| (it.price - mean) * (it.price - mean)).sum::<f64>();
+        let variance = residuals / dof as f64;
+        let half_width = t_quantile(1.0 - confidence, dof as f64);
+        let coefficients = model.raw_theta().iter().enumerate().map(|(idx, estimate)| {
+            let std_error = (variance * gram.get(idx, idx)).sqrt();
+            let t = estimate / std_error;
+            CoefficientStats { estimate: *estimate, std_error, t, p_value: t_p_value(t, dof as f64), low: estimate - half_width * std_error, high: estimate + half_width * std_error }
+        }).collect();
+        let features = model.features.len() as f64;
+        let f_statistic = ((total - residuals) / features) / variance;
+        Ok(Self {
+            names: std::iter::once("theta0".to_string()).chain(model.features.iter().cloned()).collect(),
+            rows,
+            dof,
+            confidence,
+            residual_std_error: variance.sqrt(),
+            coefficients,
+            f_statistic,
+            f_p_value: f_p_value(f_statistic, features, dof as f64),
+        })
+    }
+}
+
+impl Display for Inference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // rounded, 0.55 * 100 is not exactly 55
+        let level = format!("{}%", (self.confidence * 1e4).round() / 1e2);
+        writeln!(f, "{:<12} | {:>14} | {:>12} | {:>9} | {:>10} | {:>14} | {:>14}", "coefficient", "estimate", "std error", "t", "p-value", format!("{} low", level), format!("{} high", level))?;
+        for (name, it) in self.names.iter().zip(&self.coefficients) {
+            writeln!(f, "{:<12} | {:>14.6e} | {:>12.4e} | {:>9.3} | {:>10.3e} | {:>14.6e} | {:>14.6e}", name, it.estimate, it.std_error, it.t, it.p_value, it.low, it.high)?;
+        }
+        writeln!(f, "Residual standard error is {:.6} on {} degrees of freedom", self.residual_std_error, self.dof)?;
+        write!(f, "F-statistic is {:.4} on {} and {} degrees of freedom, p-value {:.3e}", self.f_statistic, self.names.len() - 1, self.dof, self.f_p_value)
+    }
+}
+
+/// What prediction intervals need from the training rows, saved with the model
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntervalStats {
+    /// Sum of squared residuals over the degrees of freedom
+    pub residual_variance: f64,
+    pub rows: usize,
+    /// Mean of each raw feature
+    pub means: Vec<f64>,
+    /// Sums of the products of raw feature deviations from their means, the variance of a single feature times the rows
+    pub scatter: Matrix,
+}
+
+impl IntervalStats {
+    /// From the residuals of `model` on the rows it was trained on. Fails without more rows than coefficients.
+    pub fn compute(model: &Model, dataset: &Dataset) -> Result<Self, String> {
+        let (rows, features) = (dataset.entries.len(), model.features.len());
+        if rows <= features + 1 {
+            return Err(format!("{} rows leave no degree of freedom for {} coefficients", rows, features + 1));
+        }
+        let residuals = dataset.entries.iter().map(|it| it.price - model.predict(&it.features)).map(|it| it * it).sum::<f64>();
+        let means: Vec<_> = (0..features).map(|col| dataset.entries.iter().map(|it| it.features[col]).sum::<f64>() / rows as f64).collect();
+        let mut scatter = Matrix::zeros(features, features);
+        for i in 0..features {
+            for j in 0..features {
+                scatter.set(i, j, dataset.entries.iter().map(|it| (it.features[i] - means[i]) * (it.features[j] - means[j])).sum());
+            }
+        }
+        Ok(Self { residual_variance: residuals / (rows - features - 1) as f64, rows, means, scatter })
+    }
+
+    /// Half widths of the `level` confidence interval of the mean price at `features`, then of the prediction interval of a single price
+    pub fn half_widths(&self, features: &[f64], level: f64) -> Result<(f64, f64), String> {
+        let deviation: Vec<_> = features.iter().zip(&self.means).map(|(it, mean)| it - mean).collect();
+        let solved = Qr::new(self.scatter.clone())?.solve(&deviation);
+        // leverage of the point: how far it is from the training rows, in their own spread
+        let leverage = 1.0 / self.rows as f64 + deviation.iter().zip(&solved).map(|(a, b)| a * b).sum::<f64>();
+        let t = t_quantile(1.0 - level, (self.rows - self.means.len() - 1) as f64);
+        Ok((t * (self.residual_variance * leverage).sqrt(), t * (self.residual_variance * (1.0 + leverage)).sqrt()))
+    }
+
+    /// Range of the feature at `solved` over which `price` stays within the `level` prediction interval, the other
+    /// features taken from `features` and `theta` the raw coefficients. None when the range is unbounded, which happens
+    /// when the coefficient of the feature cannot be told apart from 0 at that level.
+    pub fn inverse_interval(&self, theta: &[f64], price: f64, features: &[f64], solved: usize, level: f64) -> Result<Option<(f64, f64)>, String> {
+        // deviation from the means is base + x at `solved`
+        let mut base: Vec<_> = features.iter().zip(&self.means).map(|(it, mean)| it - mean).collect();
+        base[solved] = -self.means[solved];
+        let mut unit = vec![0.0; base.len()];
+        unit[solved] = 1.0;
+        let qr = Qr::new(self.scatter.clone())?;
+        let (solved_base, solved_unit) = (qr.solve(&base), qr.solve(&unit));
+        let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+        let t = t_quantile(1.0 - level, (self.rows - self.means.len() - 1) as f64);
+        let bound = t * t * self.residual_variance;
+        let slope = theta[solved + 1];
+        let rest = price - theta[0] - features.iter().zip(&theta[1..]).enumerate()
+            .filter(|(idx, _)| *idx != solved).map(|(_, (feature, theta))| theta * feature).sum::<f64>();
+        // (rest - slope x)² <= bound (1 + 1 / rows + leverage(x)), a quadratic a x² + b x + c <= 0
+        let a = slope * slope - bound * dot(&unit, &solved_unit);
+        let b = -2.0 * rest * slope - 2.0 * bound * dot(&base, &solved_unit);
+        let c = rest * rest - bound * (1.0 + 1.0 / self.rows as f64 + dot(&base, &solved_base));
+        let discriminant = b * b - 4.0 * a * c;
+        if a <= 0.0 || discriminant < 0.0 {
+            return Ok(None);
+        }
+        let (low, high) = ((-b - discriminant.sqrt()) / (2.0 * a), (-b + discriminant.sqrt()) / (2.0 * a));
+        Ok(Some((low, high)))
+    }
+
+    /// Section payload: residual variance as f64, rows as u64, u32 feature count, the means, then the scatter matrix row by row
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        put_f64(out, self.residual_variance);
+        put_u64(out, self.rows as u64);
+        put_u32(out, self.means.len() as u32);
+        self.means.iter().chain(&self.scatter.data).for_each(|it| put_f64(out, *it));
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        let residual_variance = reader.f64("residual variance")?;
+        let rows = reader.u64("interval rows")? as usize;
+        let features = reader.u32("interval feature count")? as usize;
+        // the means and scatter matrix take 8 bytes a value, a corrupt count must not allocate more than the file holds
+        if features.checked_mul(features + 1).and_then(|it| it.checked_mul(8)).is_none_or(|it| it > reader.remaining()) {
+            return Err(format!("{} features do not fit in the {} bytes left", features, reader.remaining()));
+        }
+        let means = (0..features).map(|idx| reader.f64(&format!("feature {} mean", idx))).collect::<Result<_, _>>()?;
+        let mut scatter = Matrix::zeros(features, features);
+        for idx in 0..features * features {
+            scatter.data[idx] = reader.f64(&format!("scatter {}", idx))?;
+        }
+        if rows <= features + 1 {
+            return Err(format!("{} rows are too few for {} features", rows, features));
+        }
+        Ok(Self { residual_variance, rows, means, scatter })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::DatasetEntry;
+
+    fn close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "{} is not {} within {}", actual, expected, tolerance);
+    }
+
+    #[test]
+    fn interval_half_widths() {
+        let residuals = [0.5, -0.5, 1.0, -1.0, 0.0, 0.0];
+        let dataset = Dataset {
+            features: vec!["km".into()],
+            entries: residuals.iter().enumerate().map(|(idx, it)| DatasetEntry { features: vec![idx as f64], price: 1.0 + 2.0 * idx as f64 + it }).collect(),
+        };
+        let model = Model { theta: vec![1.0, 2.0], features: dataset.features.clone(), ..Model::default() };
+        let stats = IntervalStats::compute(&model, &dataset).unwrap();
+        // 6 rows with a mean of 2.5 and 2.5 squared residuals on 4 degrees of freedom
+        close(stats.residual_variance, 2.5 / 4.0, 1e-14);
+        close(stats.means[0], 2.5, 1e-14);
+        close(stats.scatter.data[0], 17.5, 1e-12);
+        let t = 2.776_445_105_197_8;
+        let (mean, single) = stats.half_widths(&[4.0], 0.95).unwrap();
+        let leverage: f64 = 1.0 / 6.0 + 1.5 * 1.5 / 17.5;
+        close(mean, t * (0.625 * leverage).sqrt(), 1e-8);
+        close(single, t * (0.625 * (1.0 + leverage)).sqrt(), 1e-8);
+        let mut payload = vec![];
+        stats.encode(&mut payload);
+        assert_eq!(IntervalStats::decode(&mut ByteReader::new(&payload)), Ok(stats));
+        // a corrupt feature count fails before allocating the scatter matrix
+        payload[16..20].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(IntervalStats::decode(&mut ByteReader::new(&payload)).unwrap_err().contains("do not fit"));
+        payload[16..20].copy_from_slice(&2u32.to_be_bytes());
+        assert!(IntervalStats::decode(&mut ByteReader::new(&payload)).is_err());
+        let few = Dataset { features: dataset.features.clone(), entries: dataset.entries[..2].to_vec() };
+        assert!(IntervalStats::compute(&model, &few).is_err());
+    }
+
+    #[test]
+    fn inverse_interval_bounds_the_prediction_interval() {
+        let residuals = [0.5, -0.5, 1.0, -1.0, 0.0, 0.0];
+        let dataset = Dataset {
+            features: vec!["km".into()],
+            entries: residuals.iter().enumerate().map(|(idx, it)| DatasetEntry { features: vec![idx as f64], price: 1.0 + 2.0 * idx as f64 + it }).collect(),
+        };
+        let model = Model { theta: vec![1.0, 2.0], features: dataset.features.clone(), ..Model::default() };
+        let stats = IntervalStats::compute(&model, &dataset).unwrap();
+        let (low, high) = stats.inverse_interval(&model.theta, 7.0, &[0.0], 0, 0.95).unwrap().unwrap();
+        assert!(low < 3.0 && 3.0 < high);
+        // the price is at the edge of the prediction interval at each end
+        for x in [low, high] {
+            let single = stats.half_widths(&[x], 0.95).unwrap().1;
+            close((7.0 - model.predict(&[x])).abs(), single, 1e-9);
+        }
+        // a flat slope cannot tell feature values apart
+        assert_eq!(stats.inverse_interval(&[1.0, 0.1], 7.0, &[0.0], 0, 0.95), Ok(None));
+    }
+
+    #[test]
+    fn ln_gamma_values() {
+        close(ln_gamma(1.0), 0.0, 1e-14);
+        close(ln_gamma(5.0), 24f64.ln(), 1e-13);
+        close(ln_gamma(10.0), 362880f64.ln(), 1e-12);
+        close(ln_gamma(0.5), std::f64::consts::PI.sqrt().ln(), 1e-14);
+        close(ln_gamma(0.25), 1.288_022_524_698_077_5, 1e-13);
+    }
+
+    #[test]
+    fn incomplete_beta_closed_forms() {
+        for x in [0.0, 0.1, 0.5, 0.9, 1.0] {
+            close(incomplete_beta(x, 1.0, 1.0), x, 1e-14);
+            close(incomplete_beta(x, 3.0, 1.0), x * x * x, 1e-14);
+            close(incomplete_beta(x, 1.0, 2.5), 1.0 - (1.0 - x).powf(2.5), 1e-14);
+        }
+        close(incomplete_beta(0.5, 7.0, 7.0), 0.5, 1e-14);
+    }
+
+    #[test]
+    fn t_p_values() {
+        close(t_p_value(0.0, 5.0), 1.0, 1e-14);
+        // Cauchy: 1 - 2 atan(t) / pi
+        close(t_p_value(1.0, 1.0), 0.5, 1e-14);
+        close(t_p_value(2.0, 1.0), 0.295_167_235_300_866_5, 1e-12);
+        // two-sided 5% critical values of tables
+        close(t_p_value(12.706_204_736_174_7, 1.0), 0.05, 1e-10);
+        close(t_p_value(2.776_445_105_197_8, 4.0), 0.05, 1e-10);
+        close(t_p_value(2.228_138_851_986_3, 10.0), 0.05, 1e-10);
+        close(t_p_value(2.845_339_709_786_1, 20.0), 0.01, 1e-10);
+        close(t_p_value(-2.228_138_851_986_3, 10.0), 0.05, 1e-10);
+        // close to the normal distribution with many rows
+        close(t_p_value(1.959_963_984_540_054, 1e5), 0.050_002_772_961_786, 1e-10);
+        assert!(t_p_value(f64::NAN, 10.0).is_nan());
+    }
+
+    #[test]
+    fn t_quantiles() {
+        close(t_quantile(0.05, 10.0), 2.228_138_851_986_3, 1e-9);
+        close(t_quantile(0.01, 5.0), 4.032_142_983_555_2, 1e-9);
+        close(t_quantile(0.05, 1.0), 12.706_204_736_174_7, 1e-8);
+    }
+
+    #[test]
+    fn f_p_values() {
+        // with 2 numerator degrees of freedom, P(F > f) = (1 + 2f / d)^(-d / 2)
+        for (f, dof) in [(0.5, 3.0), (4.0, 10.0), (20.0, 7.0)] {
+            close(f_p_value(f, 2.0, dof), (1.0 + 2.0 * f / dof).powf(-dof / 2.0), 1e-13);
+        }
+        // an F(1, d) variable is the square of a t(d) one
+        close(f_p_value(2.228_138_851_986_3f64.powi(2), 1.0, 10.0), 0.05, 1e-10);
+        // 5% critical values of tables
+        close(f_p_value(4.102_821_015_130_4, 2.0, 10.0), 0.05, 1e-10);
+        close(f_p_value(3.098_391_212_140_8, 3.0, 20.0), 0.05, 1e-10);
+        assert!(f_p_value(f64::NAN, 1.0, 10.0).is_nan());
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Count of SIGINT and SIGTERM received since [install]
+static SIGNALS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(unix)]
+mod sys {
+    use std::os::raw::c_int;
+
+    pub const SIGINT: c_int = 2;
+    pub const SIGTERM: c_int = 15;
+
+    extern "C" {
+        pub fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+        pub fn _exit(status: c_int) -> !;
+    }
+}
+
+/// Only touches an atomic, or exits right away on the second signal: nothing else is safe in a signal handler
+#[cfg(unix)]
+extern "C" fn handle(_: std::os::raw::c_int) {
+    if SIGNALS.fetch_add(1, Ordering::SeqCst) > 0 {
+        unsafe { sys::_exit(130) }
+    }
+}
+
+/// Catches SIGINT and SIGTERM so training loops can stop at an iteration boundary, see [interrupted].
+/// A second signal exits immediately. Does nothing on platforms without signals.
+pub fn install() {
+    #[cfg(unix)]
+    unsafe {
+        sys::signal(sys::SIGINT, handle);
+        sys::signal(sys::SIGTERM, handle);
+    }
+}
+
+/// Whether a stop was asked for since [install]
+#[inline]
+pub fn interrupted() -> bool {
+    SIGNALS.load(Ordering::Relaxed) != 0
+}
@@ -1,6 +1,24 @@
-#![feature(const_fn_floating_point_arithmetic)]
-
 pub mod estimate_price;
 pub mod args;
 pub mod theta;
-pub mod dataset;
\ No newline at end of file
+pub mod dataset;
+pub mod scaler;
+pub mod solver;
+pub mod convergence;
+pub mod random;
+pub mod optimizer;
+pub mod schedule;
+pub mod penalty;
+pub mod history;
+pub mod export;
+pub mod checkpoint;
+pub mod interrupt;
+pub mod metrics;
+pub mod validation;
+pub mod search;
+pub mod inference;
+pub mod range;
+pub mod csv;
+pub mod locale;
+pub mod linalg;
+mod codec;
\ No newline at end of file
@@ -0,0 +1,99 @@
+/// Dense row-major matrix, just enough for the least squares problems of this crate
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Self { rows, cols, data: vec![0.0; rows * cols] }
+    }
+
+    #[inline]
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.data[row * self.cols + col]
+    }
+
+    #[inline]
+    pub fn set(&mut self, row: usize, col: usize, value: f64) {
+        self.data[row * self.cols + col] = value
+    }
+}
+
+/// Householder QR decomposition of a tall matrix, `a = Q R`
+pub struct Qr {
+    /// R in the upper triangle, Q is only kept as its reflectors
+    r: Matrix,
+    reflectors: Vec<Vec<f64>>,
+}
+
+impl Qr {
+    /// Fails when the columns of `a` are linearly dependent, or when there are fewer rows than columns
+    pub fn new(mut a: Matrix) -> Result<Self, String> {
+        if a.rows < a.cols {
+            return Err(format!("{} rows are not enough to fit {} coefficients", a.rows, a.cols));
+        }
+        let mut reflectors = Vec::with_capacity(a.cols);
+        for k in 0..a.cols {
+            let norm = (k..a.rows).map(|i| a.get(i, k) * a.get(i, k)).sum::<f64>().sqrt();
+            let alpha = if a.get(k, k) > 0.0 { -norm } else { norm };
+            let mut v: Vec<f64> = (k..a.rows).map(|i| a.get(i, k)).collect();
+            v[0] -= alpha;
+            let v_norm = v.iter().map(|it| it * it).sum::<f64>();
+            if v_norm > 0.0 {
+                for j in k..a.cols {
+                    let s = 2.0 * (k..a.rows).map(|i| v[i - k] * a.get(i, j)).sum::<f64>() / v_norm;
+                    (k..a.rows).for_each(|i| a.set(i, j, a.get(i, j) - s * v[i - k]));
+                }
+            }
+            reflectors.push(v);
+        }
+        let scale = (0..a.cols).map(|k| a.get(k, k).abs()).fold(0.0, f64::max);
+        if let Some(k) = (0..a.cols).find(|&k| a.get(k, k).abs() <= scale * 1e-12) {
+            return Err(format!("column {} is a linear combination of the others", k));
+        }
+        Ok(Self { r: a, reflectors })
+    }
+
+    /// Least squares solution of `a x = b`
+    pub fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let mut b = b.to_vec();
+        for (k, v) in self.reflectors.iter().enumerate() {
+            let v_norm = v.iter().map(|it| it * it).sum::<f64>();
+            if v_norm > 0.0 {
+                let s = 2.0 * v.iter().zip(&b[k..]).map(|(v, b)| v * b).sum::<f64>() / v_norm;
+                b[k..].iter_mut().zip(v).for_each(|(b, v)| *b -= s * v);
+            }
+        }
+        let n = self.r.cols;
+        let mut x = vec![0.0; n];
+        for k in (0..n).rev() {
+            let sum = ((k + 1)..n).map(|j| self.r.get(k, j) * x[j]).sum::<f64>();
+            x[k] = (b[k] - sum) / self.r.get(k, k);
+        }
+        x
+    }
+
+    /// `(aᵀ a)⁻¹ = R⁻¹ R⁻ᵀ`, the unscaled covariance of least squares coefficients
+    pub fn gram_inverse(&self) -> Matrix {
+        let n = self.r.cols;
+        // R⁻¹ is upper triangular, solved column by column
+        let mut inverse = Matrix::zeros(n, n);
+        for col in 0..n {
+            for k in (0..=col).rev() {
+                let identity = if k == col { 1.0 } else { 0.0 };
+                let sum = ((k + 1)..=col).map(|j| self.r.get(k, j) * inverse.get(j, col)).sum::<f64>();
+                inverse.set(k, col, (identity - sum) / self.r.get(k, k));
+            }
+        }
+        let mut gram = Matrix::zeros(n, n);
+        for i in 0..n {
+            for j in 0..n {
+                gram.set(i, j, (i.max(j)..n).map(|k| inverse.get(i, k) * inverse.get(j, k)).sum());
+            }
+        }
+        gram
+    }
+}
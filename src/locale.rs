@@ -0,0 +1,186 @@
+use crate::args::{ArgParser, BoolParser, DefaultArgParser, StringParser};
+use std::str::FromStr;
+
+pub struct DecimalArg;
+
+impl<'a> ArgParser<'a, char> for DecimalArg {
+    const NAMES: &'static [&'static str] = &["--decimal"];
+    const VALUES: &'static [&'static str] = &[".", ","];
+    const DESCRIPTION: &'static str = "Decimal separator of numbers";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<char, String> {
+        match value {
+            Some(".") => Ok('.'),
+            Some(",") => Ok(','),
+            Some(value) => Err(format!("Invalid value \"{}\", must be one of {}", value, Self::VALUES.join(", "))),
+            None => Err("Arg value is not optional, --help for more info".into()),
+        }
+    }
+}
+
+impl DefaultArgParser<'_, char> for DecimalArg {
+    const DEFAULT: char = '.';
+}
+
+pub struct ThousandsArg;
+
+impl StringParser<'_> for ThousandsArg {
+    const NAMES: &'static [&'static str] = &["--thousands"];
+    const DESCRIPTION: &'static str = "Characters grouping digits besides spaces, like . or ', skipped in numbers. \"none\" does not even skip spaces";
+}
+
+pub struct StripUnitsArg;
+
+impl BoolParser<'_> for StripUnitsArg {
+    const NAMES: &'static [&'static str] = &["--strip-units"];
+    const DESCRIPTION: &'static str = "Skips units and currencies around numbers, like km, $ or €";
+}
+
+/// Spaces used to group digits, the no-break ones included
+const SPACES: [char; 3] = [' ', '\u{a0}', '\u{202f}'];
+
+/// How numbers are written: "3 650,50 €" is 3650.5 with a decimal comma, space grouping and units stripped
+#[derive(Clone, Debug, PartialEq)]
+pub struct NumberFormat {
+    pub decimal: char,
+    /// Skipped anywhere in a number
+    pub grouping: Vec<char>,
+    /// Skips what comes before the first digit or sign and after the last digit
+    pub strip_units: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self { decimal: '.', grouping: SPACES.to_vec(), strip_units: false }
+    }
+}
+
+impl NumberFormat {
+    /// A grouping character that is also the decimal separator is left out, with an error
+    pub fn parse(input: &[String], used: &mut [bool]) -> Self {
+        let decimal = DecimalArg::parse(input, used);
+        let mut grouping = match ThousandsArg::try_parse(input, used) {
+            None => SPACES.to_vec(),
+            Some("none") => vec![],
+            Some(chars) => SPACES.iter().copied().chain(chars.chars()).collect(),
+        };
+        if grouping.contains(&decimal) {
+            println!("Error: '{}' cannot both group digits and separate decimals, ignoring it as grouping", decimal);
+            grouping.retain(|it| *it != decimal);
+        }
+        Self { decimal, grouping, strip_units: StripUnitsArg::parse(input, used) }
+    }
+
+    /// A finite number written in this format, or why it is not one. Grouping characters must split the integer part
+    /// in groups of 3 digits after the first, "12 345" is 12345 but "1 2" and "1.5" with '.' grouping are refused.
+    pub fn parse_number(&self, value: &str) -> Result<f64, String> {
+        let mut number = value.trim();
+        if self.strip_units {
+            let start = |it: char| it.is_ascii_digit() || it == '-' || it == '+' || it == self.decimal;
+            number = number.trim_start_matches(|it: char| !start(it)).trim_end_matches(|it: char| !it.is_ascii_digit());
+            if !number.contains(|it: char| it.is_ascii_digit()) {
+                return Err(format!("no number in \"{}\"", value));
+            }
+        }
+        if self.decimal != '.' && !self.grouping.contains(&'.') && number.contains('.') {
+            return Err(format!("\"{}\" has a '.' but decimals are separated by '{}'", value, self.decimal));
+        }
+        let mantissa = number.split(['e', 'E']).next().unwrap_or(number);
+        let (integer, fraction) = mantissa.split_once(self.decimal).unwrap_or((mantissa, ""));
+        if fraction.contains(|it: char| self.grouping.contains(&it)) {
+            return Err(format!("\"{}\" groups digits after the decimal separator", value));
+        }
+        let integer = integer.trim_start_matches(['-', '+']);
+        if integer.contains(|it: char| self.grouping.contains(&it)) {
+            let groups: Vec<_> = integer.split(|it: char| self.grouping.contains(&it)).collect();
+            let digits = |group: &str| group.chars().all(|it| it.is_ascii_digit());
+            if !(1..=3).contains(&groups[0].len()) || !digits(groups[0]) || groups[1..].iter().any(|it| it.len() != 3 || !digits(it)) {
+                return Err(format!("\"{}\" does not group digits by 3", value));
+            }
+        }
+        let number: String = number.chars().filter(|it| !self.grouping.contains(it)).collect();
+        let number = f64::from_str(&number.replace(self.decimal, ".")).map_err(|err| format!("\"{}\" is not a number: {}", value, err))?;
+        if number.is_finite() {
+            Ok(number)
+        } else {
+            Err(format!("\"{}\" is not a finite number", value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number_format(decimal: char, grouping: &[char], strip_units: bool) -> NumberFormat {
+        NumberFormat { decimal, grouping: grouping.to_vec(), strip_units }
+    }
+
+    #[test]
+    fn default() {
+        let format = NumberFormat::default();
+        assert_eq!(format.parse_number("3650"), Ok(3650.0));
+        assert_eq!(format.parse_number(" -12.5 "), Ok(-12.5));
+        assert_eq!(format.parse_number("1e3"), Ok(1000.0));
+        assert_eq!(format.parse_number("240 000"), Ok(240000.0));
+        assert_eq!(format.parse_number("1\u{a0}234\u{202f}567.25"), Ok(1234567.25));
+        assert!(format.parse_number("3,5").is_err());
+        assert!(format.parse_number("").is_err());
+    }
+
+    #[test]
+    fn decimal_comma() {
+        let format = number_format(',', &[' ', '.'], false);
+        assert_eq!(format.parse_number("3,5"), Ok(3.5));
+        assert_eq!(format.parse_number("1.234,5"), Ok(1234.5));
+        assert_eq!(format.parse_number("-1 234 567,125"), Ok(-1234567.125));
+        assert_eq!(format.parse_number("1,5e2"), Ok(150.0));
+    }
+
+    #[test]
+    fn stray_dot() {
+        let format = number_format(',', &[' '], false);
+        assert_eq!(format.parse_number("3.5"), Err("\"3.5\" has a '.' but decimals are separated by ','".into()));
+    }
+
+    #[test]
+    fn groups_of_3() {
+        let format = number_format(',', &[' ', '.'], false);
+        assert_eq!(format.parse_number("1.5"), Err("\"1.5\" does not group digits by 3".into()));
+        assert_eq!(format.parse_number("1 2"), Err("\"1 2\" does not group digits by 3".into()));
+        assert_eq!(format.parse_number("1234 567"), Err("\"1234 567\" does not group digits by 3".into()));
+        assert_eq!(format.parse_number("1 2345"), Err("\"1 2345\" does not group digits by 3".into()));
+        assert_eq!(format.parse_number(" 123"), Ok(123.0));
+        assert!(format.parse_number("1  234").is_err());
+        assert!(format.parse_number("1 234 ").is_ok());
+    }
+
+    #[test]
+    fn no_grouping_after_decimal() {
+        let format = number_format(',', &[' ', '.'], false);
+        assert_eq!(format.parse_number("1,234 5"), Err("\"1,234 5\" groups digits after the decimal separator".into()));
+        assert_eq!(format.parse_number("1,234.5"), Err("\"1,234.5\" groups digits after the decimal separator".into()));
+    }
+
+    #[test]
+    fn strip_units() {
+        let format = number_format(',', &[' '], true);
+        assert_eq!(format.parse_number("3 650,50 €"), Ok(3650.5));
+        assert_eq!(format.parse_number("€ -12"), Ok(-12.0));
+        assert_eq!(format.parse_number("240 000 km"), Ok(240000.0));
+        assert_eq!(format.parse_number("x"), Err("no number in \"x\"".into()));
+        assert_eq!(format.parse_number("km"), Err("no number in \"km\"".into()));
+        assert_eq!(format.parse_number("3 65 €"), Err("\"3 65 €\" does not group digits by 3".into()));
+    }
+
+    #[test]
+    fn not_finite() {
+        let format = NumberFormat::default();
+        assert_eq!(format.parse_number("nan"), Err("\"nan\" is not a finite number".into()));
+        assert_eq!(format.parse_number("-inf"), Err("\"-inf\" is not a finite number".into()));
+        assert_eq!(format.parse_number("1e400"), Err("\"1e400\" is not a finite number".into()));
+        let format = number_format(',', &[' '], true);
+        assert!(format.parse_number("inf €").is_err());
+        assert!(format.parse_number("NaN").is_err());
+    }
+}
@@ -0,0 +1,287 @@
+use crate::args::{F64Parser, UsizeParser, FileParser, DefaultArgParser};
+use crate::codec::{put_u8, put_u64, put_f64, ByteReader};
+use crate::dataset::Dataset;
+use crate::random::Random;
+use crate::export::{Export, csv_number, json_number};
+use crate::theta::Model;
+
+pub struct TestFractionArg;
+
+impl F64Parser<'_> for TestFractionArg {
+    const NAMES: &'static [&'static str] = &["--test-fraction"];
+    const DESCRIPTION: &'static str = "Share of the rows held out to evaluate the model, 0 trains on every row";
+}
+
+impl DefaultArgParser<'_, f64> for TestFractionArg {
+    const DEFAULT: f64 = 0.0;
+}
+
+pub struct MetricsExportArg;
+
+impl FileParser<'_> for MetricsExportArg {
+    const NAMES: &'static [&'static str] = &["--export"];
+    const DESCRIPTION: &'static str = "Exports the scores, as json if the file ends with .json, csv otherwise";
+}
+
+pub struct SplitSeedArg;
+
+impl UsizeParser<'_> for SplitSeedArg {
+    const NAMES: &'static [&'static str] = &["--split-seed"];
+    const DESCRIPTION: &'static str = "Seed of the holdout split, taken from the clock when not set";
+}
+
+/// A random holdout split, saved with the model so the same rows can be held out again
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Split {
+    pub test_fraction: f64,
+    pub seed: u64,
+}
+
+impl Split {
+    /// Rows held out, rounded, keeping at least one row on each side when there are two or more
+    pub fn test_count(&self, rows: usize) -> usize {
+        let count = (rows as f64 * self.test_fraction).round() as usize;
+        if rows < 2 || self.test_fraction <= 0.0 {
+            0
+        } else {
+            count.clamp(1, rows - 1)
+        }
+    }
+
+    /// The training then the test partition, rows keep their dataset order in both
+    pub fn apply(&self, dataset: &Dataset) -> (Dataset, Dataset) {
+        let mut order: Vec<usize> = (0..dataset.entries.len()).collect();
+        Random::new(self.seed).shuffle(&mut order);
+        let (test, train) = order.split_at_mut(self.test_count(dataset.entries.len()));
+        let pick = |rows: &mut [usize]| {
+            rows.sort_unstable();
+            Dataset { features: dataset.features.clone(), entries: rows.iter().map(|idx| dataset.entries[*idx].clone()).collect() }
+        };
+        (pick(train), pick(test))
+    }
+
+    /// Section payload: test fraction as f64, then the seed as u64
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        put_f64(out, self.test_fraction);
+        put_u64(out, self.seed);
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        Ok(Self {
+            test_fraction: reader.f64("test fraction")?,
+            seed: reader.u64("split seed")?,
+        })
+    }
+}
+
+/// Prediction errors over a set of rows, in raw price units. NaN when undefined:
+/// every metric without rows, MAPE when every price is 0, R² when every price is the same,
+/// adjusted R² when there are not more rows than coefficients.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Metrics {
+    pub count: usize,
+    pub mse: f64,
+    pub rmse: f64,
+    pub mae: f64,
+    /// Median absolute error, less sensitive to outliers than the MAE
+    pub median_ae: f64,
+    /// Largest absolute error
+    pub max_error: f64,
+    /// Mean absolute percentage error, rows priced 0 are left out
+    pub mape: f64,
+    pub r2: f64,
+    /// R² penalized by the number of features, to compare models with different features
+    pub adjusted_r2: f64,
+}
+
+impl Metrics {
+    /// From (actual, predicted) pairs of a model with `features` features
+    pub fn compute(pairs: &[(f64, f64)], features: usize) -> Self {
+        let count = pairs.len() as f64;
+        let mean = pairs.iter().map(|(actual, _)| actual).sum::<f64>() / count;
+        let squares = pairs.iter().map(|(actual, predicted)| (actual - predicted) * (actual - predicted)).sum::<f64>();
+        let total = pairs.iter().map(|(actual, _)| (actual - mean) * (actual - mean)).sum::<f64>();
+        let (percents, priced) = pairs.iter().filter(|(actual, _)| *actual != 0.0)
+            .fold((0.0, 0), |(sum, count), (actual, predicted)| (sum + ((actual - predicted) / actual).abs(), count + 1));
+        let mut errors: Vec<_> = pairs.iter().map(|(actual, predicted)| (actual - predicted).abs()).collect();
+        errors.sort_unstable_by(f64::total_cmp);
+        let median_ae = match errors.len() {
+            0 => f64::NAN,
+            len if len % 2 == 0 => (errors[len / 2 - 1] + errors[len / 2]) / 2.0,
+            len => errors[len / 2],
+        };
+        let mse = squares / count;
+        let r2 = if total == 0.0 { f64::NAN } else { 1.0 - squares / total };
+        let freedom = pairs.len() as isize - features as isize - 1;
+        Self {
+            count: pairs.len(),
+            mse,
+            rmse: mse.sqrt(),
+            mae: errors.iter().sum::<f64>() / count,
+            median_ae,
+            max_error: errors.last().copied().unwrap_or(f64::NAN),
+            mape: if priced == 0 { f64::NAN } else { 100.0 * percents / priced as f64 },
+            r2,
+            adjusted_r2: if freedom > 0 { 1.0 - (1.0 - r2) * (count - 1.0) / freedom as f64 } else { f64::NAN },
+        }
+    }
+
+    /// Predictions of `model` on the raw rows of `dataset`
+    pub fn of(model: &Model, dataset: &Dataset) -> Self {
+        let pairs: Vec<_> = dataset.entries.iter().map(|it| (it.price, model.predict(&it.features))).collect();
+        Self::compute(&pairs, model.features.len())
+    }
+
+    /// In the order of [NAMES]
+    pub(crate) fn values(&self) -> [f64; 8] {
+        [self.mse, self.rmse, self.mae, self.median_ae, self.max_error, self.mape, self.r2, self.adjusted_r2]
+    }
+
+    pub(crate) fn from_values(count: usize, [mse, rmse, mae, median_ae, max_error, mape, r2, adjusted_r2]: [f64; 8]) -> Self {
+        Self { count, mse, rmse, mae, median_ae, max_error, mape, r2, adjusted_r2 }
+    }
+
+    /// Payload: count as u64, then each metric as f64 in the order of [NAMES]
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        put_u64(out, self.count as u64);
+        self.values().iter().for_each(|it| put_f64(out, *it));
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        let count = reader.u64("metrics row count")? as usize;
+        let mut values = [0.0; 8];
+        for (idx, value) in values.iter_mut().enumerate() {
+            *value = reader.f64(NAMES[idx])?;
+        }
+        Ok(Self::from_values(count, values))
+    }
+}
+
+impl Export for Metrics {
+    /// A header of the [KEYS] then one row of values, undefined ones are left empty
+    fn to_csv(&self) -> String {
+        let values: Vec<_> = self.values().iter().map(|it| csv_number(*it)).collect();
+        format!("count,{}\n{},{}\n", KEYS.join(","), self.count, values.join(","))
+    }
+
+    /// An object keyed by the [KEYS], non finite values are null
+    fn to_json(&self) -> String {
+        let values: Vec<_> = KEYS.iter().zip(self.values()).map(|(key, value)| format!("  \"{}\": {}", key, json_number(value))).collect();
+        format!("{{\n  \"count\": {},\n{}\n}}\n", self.count, values.join(",\n"))
+    }
+}
+
+/// Metrics of the training rows, and of the held out rows if any
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Evaluation {
+    pub train: Metrics,
+    pub test: Option<Metrics>,
+}
+
+impl Evaluation {
+    /// Section payload: the training [Metrics], then u8 1 and the test [Metrics], or u8 0 without test rows
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        self.train.encode(out);
+        match &self.test {
+            Some(test) => {
+                put_u8(out, 1);
+                test.encode(out);
+            }
+            None => put_u8(out, 0),
+        }
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        let train = Metrics::decode(reader)?;
+        let test = match reader.u8("test metrics flag")? {
+            0 => None,
+            1 => Some(Metrics::decode(reader)?),
+            flag => return Err(format!("invalid test metrics flag {}", flag)),
+        };
+        Ok(Self { train, test })
+    }
+}
+
+/// Labels of the metrics, as [table] prints them
+const NAMES: [&str; 8] = ["MSE", "RMSE", "MAE", "MedAE", "max err", "MAPE %", "R²", "adj R²"];
+
+/// Keys of the metrics in exported files, in the order of [NAMES]
+const KEYS: [&str; 8] = ["mse", "rmse", "mae", "median_ae", "max_error", "mape", "r2", "adjusted_r2"];
+
+/// One line per metric, one column per named set of metrics
+pub fn table(columns: &[(&str, &Metrics)]) -> String {
+    let mut lines = vec![format!("{:<8}{}", "", columns.iter().map(|(name, _)| format!(" | {:>14}", name)).collect::<String>())];
+    lines.push(format!("{:<8}{}", "rows", columns.iter().map(|(_, it)| format!(" | {:>14}", it.count)).collect::<String>()));
+    for (idx, name) in NAMES.iter().enumerate() {
+        lines.push(format!("{:<8}{}", name, columns.iter().map(|(_, it)| format!(" | {:>14.6}", it.values()[idx])).collect::<String>()));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::DatasetEntry;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-12 * a.abs().max(b.abs()).max(1.0)
+    }
+
+    #[test]
+    fn fixed_values() {
+        // errors are -1, 1, 0 and 2
+        let metrics = Metrics::compute(&[(1.0, 2.0), (2.0, 1.0), (3.0, 3.0), (4.0, 2.0)], 1);
+        assert_eq!(metrics.count, 4);
+        assert!(close(metrics.mse, 1.5));
+        assert!(close(metrics.rmse, 1.5f64.sqrt()));
+        assert!(close(metrics.mae, 1.0));
+        assert!(close(metrics.mape, 100.0 * (1.0 + 0.5 + 0.0 + 0.5) / 4.0));
+        // the prices vary by 5 around their mean of 2.5
+        assert!(close(metrics.r2, 1.0 - 6.0 / 5.0));
+        // the absolute errors sorted are 0, 1, 1 and 2
+        assert!(close(metrics.median_ae, 1.0));
+        assert!(close(metrics.max_error, 2.0));
+        // 4 rows and 1 feature leave 2 degrees of freedom
+        assert!(close(metrics.adjusted_r2, 1.0 - 1.2 * 3.0 / 2.0));
+        assert!(close(Metrics::compute(&[(1.0, 2.0), (2.0, 1.0), (3.0, 3.0)], 0).median_ae, 1.0));
+    }
+
+    #[test]
+    fn undefined_values_are_nan() {
+        let perfect = Metrics::compute(&[(1.0, 1.0), (2.0, 2.0)], 0);
+        assert_eq!((perfect.mse, perfect.r2), (0.0, 1.0));
+        let constant = Metrics::compute(&[(0.0, 1.0), (0.0, -1.0)], 0);
+        assert!(constant.mape.is_nan() && constant.r2.is_nan());
+        assert!(Metrics::compute(&[], 0).mse.is_nan());
+        // as many features as rows past the first leave no degree of freedom
+        assert!(Metrics::compute(&[(1.0, 1.0), (2.0, 2.0)], 1).adjusted_r2.is_nan());
+    }
+
+    #[test]
+    fn csv_and_json() {
+        let metrics = Metrics::compute(&[(1.0, 1.0), (2.0, 2.0)], 1);
+        assert_eq!(metrics.to_csv(), "count,mse,rmse,mae,median_ae,max_error,mape,r2,adjusted_r2\n2,0,0,0,0,0,0,1,\n");
+        let json = metrics.to_json();
+        assert!(json.starts_with("{\n  \"count\": 2,\n  \"mse\": 0e0,\n"));
+        assert!(json.ends_with("  \"r2\": 1e0,\n  \"adjusted_r2\": null\n}\n"));
+    }
+
+    #[test]
+    fn split_partitions_the_rows() {
+        let dataset = Dataset {
+            features: vec!["km".into()],
+            entries: (0..10).map(|idx| DatasetEntry { features: vec![idx as f64], price: 0.0 }).collect(),
+        };
+        let split = Split { test_fraction: 0.3, seed: 11 };
+        let (train, test) = split.apply(&dataset);
+        assert_eq!((train.entries.len(), test.entries.len()), (7, 3));
+        let mut rows: Vec<_> = train.entries.iter().chain(&test.entries).map(|it| it.features[0]).collect();
+        rows.sort_by(f64::total_cmp);
+        assert_eq!(rows, (0..10).map(|idx| idx as f64).collect::<Vec<_>>());
+        let again = split.apply(&dataset).1;
+        assert!(test.entries.iter().zip(&again.entries).all(|(a, b)| a.features == b.features));
+        assert_eq!(Split { test_fraction: 0.01, seed: 0 }.test_count(10), 1);
+        assert_eq!(Split { test_fraction: 0.99, seed: 0 }.test_count(10), 9);
+        assert_eq!(Split { test_fraction: 0.5, seed: 0 }.test_count(1), 0);
+    }
+}
@@ -0,0 +1,390 @@
+use crate::args::{ArgParser, DefaultArgParser, F64Parser};
+use crate::codec::{put_u8, put_f64, ByteReader};
+
+pub struct OptimizerArg;
+
+impl<'a> ArgParser<'a, OptimizerName> for OptimizerArg {
+    const NAMES: &'static [&'static str] = &["--optimizer"];
+    const VALUES: &'static [&'static str] = &["gd", "momentum", "nesterov", "rmsprop", "adagrad", "adam"];
+    const DESCRIPTION: &'static str = "How gradient descent turns gradients into updates";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<OptimizerName, String> {
+        match value {
+            Some("gd") => Ok(OptimizerName::Vanilla),
+            Some("momentum") => Ok(OptimizerName::Momentum),
+            Some("nesterov") => Ok(OptimizerName::Nesterov),
+            Some("rmsprop") => Ok(OptimizerName::RmsProp),
+            Some("adagrad") => Ok(OptimizerName::Adagrad),
+            Some("adam") => Ok(OptimizerName::Adam),
+            Some(value) => Err(format!("Invalid value \"{}\", must be one of {}", value, Self::VALUES.join(", "))),
+            None => Err("Arg value is not optional, --help for more info".into()),
+        }
+    }
+}
+
+impl DefaultArgParser<'_, OptimizerName> for OptimizerArg {
+    const DEFAULT: OptimizerName = OptimizerName::Vanilla;
+}
+
+pub struct MomentumArg;
+
+impl F64Parser<'_> for MomentumArg {
+    const NAMES: &'static [&'static str] = &["--momentum"];
+    const DESCRIPTION: &'static str = "Fraction of the previous update kept by momentum and nesterov";
+}
+
+impl DefaultArgParser<'_, f64> for MomentumArg {
+    const DEFAULT: f64 = 0.9;
+}
+
+pub struct RhoArg;
+
+impl F64Parser<'_> for RhoArg {
+    const NAMES: &'static [&'static str] = &["--rho"];
+    const DESCRIPTION: &'static str = "Decay of the squared gradient average of rmsprop";
+}
+
+impl DefaultArgParser<'_, f64> for RhoArg {
+    const DEFAULT: f64 = 0.9;
+}
+
+pub struct Beta1Arg;
+
+impl F64Parser<'_> for Beta1Arg {
+    const NAMES: &'static [&'static str] = &["--beta1"];
+    const DESCRIPTION: &'static str = "Decay of the gradient average of adam";
+}
+
+impl DefaultArgParser<'_, f64> for Beta1Arg {
+    const DEFAULT: f64 = 0.9;
+}
+
+pub struct Beta2Arg;
+
+impl F64Parser<'_> for Beta2Arg {
+    const NAMES: &'static [&'static str] = &["--beta2"];
+    const DESCRIPTION: &'static str = "Decay of the squared gradient average of adam";
+}
+
+impl DefaultArgParser<'_, f64> for Beta2Arg {
+    const DEFAULT: f64 = 0.999;
+}
+
+pub struct EpsilonArg;
+
+impl F64Parser<'_> for EpsilonArg {
+    const NAMES: &'static [&'static str] = &["--epsilon"];
+    const DESCRIPTION: &'static str = "Keeps rmsprop, adagrad and adam from dividing by zero";
+}
+
+impl DefaultArgParser<'_, f64> for EpsilonArg {
+    const DEFAULT: f64 = 1e-8;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OptimizerName {
+    Vanilla,
+    Momentum,
+    Nesterov,
+    RmsProp,
+    Adagrad,
+    Adam,
+}
+
+/// An optimizer and its hyperparameters
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum OptimizerKind {
+    #[default]
+    Vanilla,
+    Momentum { momentum: f64 },
+    Nesterov { momentum: f64 },
+    RmsProp { rho: f64, epsilon: f64 },
+    Adagrad { epsilon: f64 },
+    Adam { beta1: f64, beta2: f64, epsilon: f64 },
+}
+
+impl OptimizerKind {
+    /// Reads the optimizer and only the hyperparameters it uses, the others are left unused so they get reported
+    pub fn parse(input: &[String], used: &mut [bool]) -> Self {
+        match OptimizerArg::parse(input, used) {
+            OptimizerName::Vanilla => OptimizerKind::Vanilla,
+            OptimizerName::Momentum => OptimizerKind::Momentum { momentum: MomentumArg::parse(input, used) },
+            OptimizerName::Nesterov => OptimizerKind::Nesterov { momentum: MomentumArg::parse(input, used) },
+            OptimizerName::RmsProp => OptimizerKind::RmsProp { rho: RhoArg::parse(input, used), epsilon: EpsilonArg::parse(input, used) },
+            OptimizerName::Adagrad => OptimizerKind::Adagrad { epsilon: EpsilonArg::parse(input, used) },
+            OptimizerName::Adam => OptimizerKind::Adam { beta1: Beta1Arg::parse(input, used), beta2: Beta2Arg::parse(input, used), epsilon: EpsilonArg::parse(input, used) },
+        }
+    }
+
+    pub(crate) fn id(&self) -> u8 {
+        match self {
+            OptimizerKind::Vanilla => 0,
+            OptimizerKind::Momentum { .. } => 1,
+            OptimizerKind::Nesterov { .. } => 2,
+            OptimizerKind::RmsProp { .. } => 3,
+            OptimizerKind::Adagrad { .. } => 4,
+            OptimizerKind::Adam { .. } => 5,
+        }
+    }
+
+    /// Kind as u8, then its three hyperparameters as f64, unused ones being 0
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        let hyperparameters = match *self {
+            OptimizerKind::Vanilla => [0.0; 3],
+            OptimizerKind::Momentum { momentum } | OptimizerKind::Nesterov { momentum } => [momentum, 0.0, 0.0],
+            OptimizerKind::RmsProp { rho, epsilon } => [rho, epsilon, 0.0],
+            OptimizerKind::Adagrad { epsilon } => [epsilon, 0.0, 0.0],
+            OptimizerKind::Adam { beta1, beta2, epsilon } => [beta1, beta2, epsilon],
+        };
+        put_u8(out, self.id());
+        hyperparameters.iter().for_each(|it| put_f64(out, *it));
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        let id = reader.u8("optimizer kind")?;
+        let mut hyperparameters = [0.0; 3];
+        for it in hyperparameters.iter_mut() {
+            *it = reader.f64("optimizer hyperparameter")?;
+        }
+        let [first, second, third] = hyperparameters;
+        match id {
+            0 => Ok(OptimizerKind::Vanilla),
+            1 => Ok(OptimizerKind::Momentum { momentum: first }),
+            2 => Ok(OptimizerKind::Nesterov { momentum: first }),
+            3 => Ok(OptimizerKind::RmsProp { rho: first, epsilon: second }),
+            4 => Ok(OptimizerKind::Adagrad { epsilon: first }),
+            5 => Ok(OptimizerKind::Adam { beta1: first, beta2: second, epsilon: third }),
+            _ => Err(format!("unknown optimizer kind {}", id)),
+        }
+    }
+
+    /// A fresh optimizer for `len` coefficients
+    pub fn build(&self, len: usize) -> Box<dyn Optimizer> {
+        match *self {
+            OptimizerKind::Vanilla => Box::new(Vanilla),
+            OptimizerKind::Momentum { momentum } => Box::new(Momentum { momentum, velocity: vec![0.0; len] }),
+            OptimizerKind::Nesterov { momentum } => Box::new(Nesterov { momentum, velocity: vec![0.0; len] }),
+            OptimizerKind::RmsProp { rho, epsilon } => Box::new(RmsProp { rho, epsilon, squares: vec![0.0; len] }),
+            OptimizerKind::Adagrad { epsilon } => Box::new(Adagrad { epsilon, squares: vec![0.0; len] }),
+            OptimizerKind::Adam { beta1, beta2, epsilon } => Box::new(Adam { beta1, beta2, epsilon, step: 0, means: vec![0.0; len], squares: vec![0.0; len] }),
+        }
+    }
+}
+
+/// Turns the mean gradient of a batch into an update of theta
+pub trait Optimizer {
+    fn step(&mut self, theta: &mut [f64], gradient: &[f64], ratio: f64);
+    /// Everything the optimizer accumulated, so a checkpoint can resume it exactly
+    fn state(&self) -> Vec<f64>;
+    /// Takes back a [Optimizer::state] of an optimizer of the same kind and length
+    fn restore(&mut self, state: &[f64]);
+}
+
+/// theta -= ratio * gradient
+pub struct Vanilla;
+
+impl Optimizer for Vanilla {
+    fn step(&mut self, theta: &mut [f64], gradient: &[f64], ratio: f64) {
+        theta.iter_mut().zip(gradient).for_each(|(theta, gradient)| *theta -= gradient * ratio);
+    }
+
+    fn state(&self) -> Vec<f64> {
+        vec![]
+    }
+
+    fn restore(&mut self, _: &[f64]) {}
+}
+
+/// Accumulates a velocity so consistent directions speed up and oscillations cancel out
+pub struct Momentum {
+    momentum: f64,
+    velocity: Vec<f64>,
+}
+
+impl Optimizer for Momentum {
+    fn step(&mut self, theta: &mut [f64], gradient: &[f64], ratio: f64) {
+        for ((theta, velocity), gradient) in theta.iter_mut().zip(&mut self.velocity).zip(gradient) {
+            *velocity = self.momentum * *velocity - ratio * gradient;
+            *theta += *velocity;
+        }
+    }
+
+    fn state(&self) -> Vec<f64> {
+        self.velocity.clone()
+    }
+
+    fn restore(&mut self, state: &[f64]) {
+        self.velocity.copy_from_slice(state);
+    }
+}
+
+/// Momentum with the gradient taken ahead of the velocity, written in the form that only needs the gradient at theta
+pub struct Nesterov {
+    momentum: f64,
+    velocity: Vec<f64>,
+}
+
+impl Optimizer for Nesterov {
+    fn step(&mut self, theta: &mut [f64], gradient: &[f64], ratio: f64) {
+        for ((theta, velocity), gradient) in theta.iter_mut().zip(&mut self.velocity).zip(gradient) {
+            *velocity = self.momentum * *velocity - ratio * gradient;
+            *theta += self.momentum * *velocity - ratio * gradient;
+        }
+    }
+
+    fn state(&self) -> Vec<f64> {
+        self.velocity.clone()
+    }
+
+    fn restore(&mut self, state: &[f64]) {
+        self.velocity.copy_from_slice(state);
+    }
+}
+
+/// Divides each step by a decaying average of the squared gradients
+pub struct RmsProp {
+    rho: f64,
+    epsilon: f64,
+    squares: Vec<f64>,
+}
+
+impl Optimizer for RmsProp {
+    fn step(&mut self, theta: &mut [f64], gradient: &[f64], ratio: f64) {
+        for ((theta, square), gradient) in theta.iter_mut().zip(&mut self.squares).zip(gradient) {
+            *square = self.rho * *square + (1.0 - self.rho) * gradient * gradient;
+            *theta -= ratio * gradient / (square.sqrt() + self.epsilon);
+        }
+    }
+
+    fn state(&self) -> Vec<f64> {
+        self.squares.clone()
+    }
+
+    fn restore(&mut self, state: &[f64]) {
+        self.squares.copy_from_slice(state);
+    }
+}
+
+/// Divides each step by the root of all the squared gradients seen so far
+pub struct Adagrad {
+    epsilon: f64,
+    squares: Vec<f64>,
+}
+
+impl Optimizer for Adagrad {
+    fn step(&mut self, theta: &mut [f64], gradient: &[f64], ratio: f64) {
+        for ((theta, square), gradient) in theta.iter_mut().zip(&mut self.squares).zip(gradient) {
+            *square += gradient * gradient;
+            *theta -= ratio * gradient / (square.sqrt() + self.epsilon);
+        }
+    }
+
+    fn state(&self) -> Vec<f64> {
+        self.squares.clone()
+    }
+
+    fn restore(&mut self, state: &[f64]) {
+        self.squares.copy_from_slice(state);
+    }
+}
+
+/// Momentum on the gradient and rmsprop scaling, both bias corrected
+pub struct Adam {
+    beta1: f64,
+    beta2: f64,
+    epsilon: f64,
+    step: i32,
+    means: Vec<f64>,
+    squares: Vec<f64>,
+}
+
+impl Optimizer for Adam {
+    fn step(&mut self, theta: &mut [f64], gradient: &[f64], ratio: f64) {
+        self.step = self.step.saturating_add(1);
+        let mean_correction = 1.0 - self.beta1.powi(self.step);
+        let square_correction = 1.0 - self.beta2.powi(self.step);
+        for (((theta, mean), square), gradient) in theta.iter_mut().zip(&mut self.means).zip(&mut self.squares).zip(gradient) {
+            *mean = self.beta1 * *mean + (1.0 - self.beta1) * gradient;
+            *square = self.beta2 * *square + (1.0 - self.beta2) * gradient * gradient;
+            *theta -= ratio * (*mean / mean_correction) / ((*square / square_correction).sqrt() + self.epsilon);
+        }
+    }
+
+    /// The step count, then the means, then the squares
+    fn state(&self) -> Vec<f64> {
+        let mut state = vec![self.step as f64];
+        state.extend(&self.means);
+        state.extend(&self.squares);
+        state
+    }
+
+    fn restore(&mut self, state: &[f64]) {
+        let len = self.means.len();
+        self.step = state[0] as i32;
+        self.means.copy_from_slice(&state[1..=len]);
+        self.squares.copy_from_slice(&state[len + 1..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [OptimizerKind; 6] = [
+        OptimizerKind::Vanilla,
+        OptimizerKind::Momentum { momentum: 0.9 },
+        OptimizerKind::Nesterov { momentum: 0.9 },
+        OptimizerKind::RmsProp { rho: 0.9, epsilon: 1e-8 },
+        OptimizerKind::Adagrad { epsilon: 1e-8 },
+        OptimizerKind::Adam { beta1: 0.9, beta2: 0.999, epsilon: 1e-8 },
+    ];
+
+    #[test]
+    fn first_steps() {
+        let mut theta = [1.0, -1.0];
+        OptimizerKind::Vanilla.build(2).step(&mut theta, &[2.0, -4.0], 0.5);
+        assert_eq!(theta, [0.0, 1.0]);
+
+        let mut momentum = OptimizerKind::Momentum { momentum: 0.5 }.build(1);
+        let mut theta = [0.0];
+        momentum.step(&mut theta, &[1.0], 1.0);
+        momentum.step(&mut theta, &[1.0], 1.0);
+        assert_eq!(theta, [-2.5]);
+
+        // bias correction makes the first adam step ratio long whatever the gradient scale
+        for gradient in [1e-3, 1.0, 1e3] {
+            let mut theta = [0.0];
+            OptimizerKind::Adam { beta1: 0.9, beta2: 0.999, epsilon: 1e-12 }.build(1).step(&mut theta, &[gradient], 0.1);
+            assert!((theta[0] + 0.1).abs() < 1e-9, "{}", theta[0]);
+        }
+    }
+
+    #[test]
+    fn minimize_a_quadratic() {
+        // f(x) = (x0 - 3)² + 10 (x1 + 1)²
+        let gradient = |theta: &[f64]| [2.0 * (theta[0] - 3.0), 20.0 * (theta[1] + 1.0)];
+        for kind in &ALL {
+            let mut optimizer = kind.build(2);
+            let mut theta = [0.0, 0.0];
+            let ratio = match kind {
+                OptimizerKind::Adagrad { .. } => 0.5,
+                OptimizerKind::Vanilla | OptimizerKind::Momentum { .. } | OptimizerKind::Nesterov { .. } => 0.02,
+                _ => 0.001,
+            };
+            for _ in 0..20_000 {
+                let gradient = gradient(&theta);
+                optimizer.step(&mut theta, &gradient, ratio);
+            }
+            assert!((theta[0] - 3.0).abs() < 1e-2 && (theta[1] + 1.0).abs() < 1e-2, "{:?} ended at {:?}", kind, theta);
+        }
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        for kind in ALL {
+            let mut bytes = vec![];
+            kind.encode(&mut bytes);
+            assert_eq!(OptimizerKind::decode(&mut ByteReader::new(&bytes)), Ok(kind));
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::args::{ArgParser, DefaultArgParser, F64Parser};
+use crate::codec::{put_u8, put_f64, ByteReader};
+use std::fmt::{self, Display, Formatter};
+
+pub struct PenaltyArg;
+
+impl<'a> ArgParser<'a, PenaltyName> for PenaltyArg {
+    const NAMES: &'static [&'static str] = &["--penalty"];
+    const VALUES: &'static [&'static str] = &["none", "ridge", "lasso", "elasticnet"];
+    const DESCRIPTION: &'static str = "Regularization of the coefficients, the intercept is never penalized";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<PenaltyName, String> {
+        match value {
+            Some("none") => Ok(PenaltyName::None),
+            Some("ridge") => Ok(PenaltyName::Ridge),
+            Some("lasso") => Ok(PenaltyName::Lasso),
+            Some("elasticnet") => Ok(PenaltyName::ElasticNet),
+            Some(value) => Err(format!("Invalid value \"{}\", must be one of {}", value, Self::VALUES.join(", "))),
+            None => Err("Arg value is not optional, --help for more info".into()),
+        }
+    }
+}
+
+impl DefaultArgParser<'_, PenaltyName> for PenaltyArg {
+    const DEFAULT: PenaltyName = PenaltyName::None;
+}
+
+pub struct LambdaArg;
+
+impl F64Parser<'_> for LambdaArg {
+    const NAMES: &'static [&'static str] = &["--lambda"];
+    const DESCRIPTION: &'static str = "Strength of the penalty";
+
+    fn check(value: f64) -> Result<(), String> {
+        if value >= 0.0 && value.is_finite() { Ok(()) } else { Err("Lambda must be 0 <= L, ignoring".into()) }
+    }
+}
+
+impl DefaultArgParser<'_, f64> for LambdaArg {
+    const DEFAULT: f64 = 0.01;
+}
+
+pub struct L1RatioArg;
+
+impl F64Parser<'_> for L1RatioArg {
+    const NAMES: &'static [&'static str] = &["--l1-ratio"];
+    const DESCRIPTION: &'static str = "Share of L1 in the elastic net penalty, between 0 and 1";
+
+    fn check(value: f64) -> Result<(), String> {
+        if (0.0..=1.0).contains(&value) { Ok(()) } else { Err("L1 ratio must be 0 <= R <= 1, ignoring".into()) }
+    }
+}
+
+impl DefaultArgParser<'_, f64> for L1RatioArg {
+    const DEFAULT: f64 = 0.5;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PenaltyName {
+    None,
+    Ridge,
+    Lasso,
+    ElasticNet,
+}
+
+/// Added to half the mean squared error: `lambda × (l1_ratio × |w|₁ + (1 - l1_ratio) / 2 × |w|²)`,
+/// w being every coefficient but the intercept. Applies in the scaled space the model is trained in.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Penalty {
+    #[default]
+    None,
+    Ridge { lambda: f64 },
+    Lasso { lambda: f64 },
+    ElasticNet { lambda: f64, l1_ratio: f64 },
+}
+
+impl Penalty {
+    /// Reads the penalty and only the parameters it uses, the others are left unused so they get reported
+    pub fn parse(input: &[String], used: &mut [bool]) -> Self {
+        match PenaltyArg::parse(input, used) {
+            PenaltyName::None => Penalty::None,
+            PenaltyName::Ridge => Penalty::Ridge { lambda: LambdaArg::parse(input, used) },
+            PenaltyName::Lasso => Penalty::Lasso { lambda: LambdaArg::parse(input, used) },
+            PenaltyName::ElasticNet => Penalty::ElasticNet { lambda: LambdaArg::parse(input, used), l1_ratio: L1RatioArg::parse(input, used) },
+        }
+    }
+
+    /// Weight of the L1 norm
+    pub fn l1(&self) -> f64 {
+        match *self {
+            Penalty::None | Penalty::Ridge { .. } => 0.0,
+            Penalty::Lasso { lambda } => lambda,
+            Penalty::ElasticNet { lambda, l1_ratio } => lambda * l1_ratio,
+        }
+    }
+
+    /// Weight of half the squared L2 norm
+    pub fn l2(&self) -> f64 {
+        match *self {
+            Penalty::None | Penalty::Lasso { .. } => 0.0,
+            Penalty::Ridge { lambda } => lambda,
+            Penalty::ElasticNet { lambda, l1_ratio } => lambda * (1.0 - l1_ratio),
+        }
+    }
+
+    pub fn value(&self, theta: &[f64]) -> f64 {
+        self.l1() * theta[1..].iter().map(|it| it.abs()).sum::<f64>() + self.l2() / 2.0 * theta[1..].iter().map(|it| it * it).sum::<f64>()
+    }
+
+    /// Adds the gradient of the smooth L2 part, the L1 part goes through [Penalty::prox]
+    #[inline]
+    pub fn add_gradient(&self, theta: &[f64], gradient: &mut [f64]) {
+        let l2 = self.l2();
+        if l2 != 0.0 {
+            gradient[1..].iter_mut().zip(&theta[1..]).for_each(|(gradient, theta)| *gradient += l2 * theta);
+        }
+    }
+
+    /// Proximal step of the L1 part after a gradient step of size `ratio`
+    #[inline]
+    pub fn prox(&self, theta: &mut [f64], ratio: f64) {
+        let l1 = self.l1();
+        if l1 != 0.0 {
+            theta[1..].iter_mut().for_each(|it| *it = soft_threshold(*it, ratio * l1));
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            Penalty::None => 0,
+            Penalty::Ridge { .. } => 1,
+            Penalty::Lasso { .. } => 2,
+            Penalty::ElasticNet { .. } => 3,
+        }
+    }
+
+    /// Section payload: kind as u8, lambda as f64, then the l1 ratio as f64
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        let (lambda, l1_ratio) = match *self {
+            Penalty::None => (0.0, 0.0),
+            Penalty::Ridge { lambda } => (lambda, 0.0),
+            Penalty::Lasso { lambda } => (lambda, 1.0),
+            Penalty::ElasticNet { lambda, l1_ratio } => (lambda, l1_ratio),
+        };
+        put_u8(out, self.id());
+        put_f64(out, lambda);
+        put_f64(out, l1_ratio);
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        let id = reader.u8("penalty kind")?;
+        let lambda = reader.f64("penalty strength")?;
+        let l1_ratio = reader.f64("penalty l1 ratio")?;
+        let valid = lambda >= 0.0 && lambda.is_finite() && (0.0..=1.0).contains(&l1_ratio);
+        if !valid {
+            return Err(format!("penalty strength {} or l1 ratio {} is out of range", lambda, l1_ratio));
+        }
+        match id {
+            0 => Ok(Penalty::None),
+            1 => Ok(Penalty::Ridge { lambda }),
+            2 => Ok(Penalty::Lasso { lambda }),
+            3 => Ok(Penalty::ElasticNet { lambda, l1_ratio }),
+            _ => Err(format!("unknown penalty kind {}", id)),
+        }
+    }
+}
+
+impl Display for Penalty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Penalty::None => write!(f, "none"),
+            Penalty::Ridge { lambda } => write!(f, "ridge, lambda {}", lambda),
+            Penalty::Lasso { lambda } => write!(f, "lasso, lambda {}", lambda),
+            Penalty::ElasticNet { lambda, l1_ratio } => write!(f, "elastic net, lambda {}, l1 ratio {}", lambda, l1_ratio),
+        }
+    }
+}
+
+/// Moves `value` toward 0 by `threshold`, stopping at 0
+#[inline]
+pub fn soft_threshold(value: f64, threshold: f64) -> f64 {
+    if value > threshold {
+        value - threshold
+    } else if value < -threshold {
+        value + threshold
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intercept_is_not_penalized() {
+        let theta = [10.0, 3.0, -4.0];
+        assert_eq!(Penalty::None.value(&theta), 0.0);
+        assert_eq!(Penalty::Ridge { lambda: 0.5 }.value(&theta), 6.25);
+        assert_eq!(Penalty::Lasso { lambda: 0.5 }.value(&theta), 3.5);
+        assert_eq!(Penalty::ElasticNet { lambda: 1.0, l1_ratio: 0.5 }.value(&theta), 3.5 + 6.25);
+
+        let mut gradient = [1.0, 1.0, 1.0];
+        Penalty::Ridge { lambda: 0.5 }.add_gradient(&theta, &mut gradient);
+        assert_eq!(gradient, [1.0, 2.5, -1.0]);
+    }
+
+    #[test]
+    fn prox_soft_thresholds() {
+        assert_eq!((soft_threshold(3.0, 1.0), soft_threshold(-3.0, 1.0), soft_threshold(0.5, 1.0)), (2.0, -2.0, 0.0));
+        let mut theta = [5.0, 0.5, -2.0];
+        Penalty::Lasso { lambda: 2.0 }.prox(&mut theta, 0.5);
+        assert_eq!(theta, [5.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        for penalty in [Penalty::None, Penalty::Ridge { lambda: 0.1 }, Penalty::Lasso { lambda: 0.2 }, Penalty::ElasticNet { lambda: 0.3, l1_ratio: 0.25 }] {
+            let mut bytes = vec![];
+            penalty.encode(&mut bytes);
+            assert_eq!(Penalty::decode(&mut ByteReader::new(&bytes)), Ok(penalty));
+        }
+        let mut bytes = vec![];
+        Penalty::Ridge { lambda: -1.0 }.encode(&mut bytes);
+        assert!(Penalty::decode(&mut ByteReader::new(&bytes)).is_err());
+    }
+
+    #[test]
+    fn args_are_checked() {
+        assert!(LambdaArg::parse_arg_value(Some("-0.1")).is_err());
+        assert_eq!(LambdaArg::parse_arg_value(Some("0")), Ok(0.0));
+        assert!(L1RatioArg::parse_arg_value(Some("1.5")).is_err());
+        assert_eq!(L1RatioArg::parse_arg_value(Some("1")), Ok(1.0));
+    }
+}
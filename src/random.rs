@@ -0,0 +1,56 @@
+use crate::args::UsizeParser;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct SeedArg;
+
+impl UsizeParser<'_> for SeedArg {
+    const NAMES: &'static [&'static str] = &["--seed"];
+    const DESCRIPTION: &'static str = "Seed of the shuffling, taken from the clock when not set";
+}
+
+/// A seed for runs that were not given one, printed and saved so they can still be reproduced
+pub fn seed_from_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|it| it.as_nanos() as u64).unwrap_or(0)
+}
+
+/// SplitMix64: tiny, fast, and good enough for shuffling and sampling. The same seed always gives the same sequence.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Where the sequence is at, [Random::new] with it continues the same sequence
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in [0, bound), bound must not be 0
+    pub fn below(&mut self, bound: usize) -> usize {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+
+    /// Fisher-Yates
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for idx in (1..slice.len()).rev() {
+            slice.swap(idx, self.below(idx + 1));
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::args::BoolParser;
+use crate::codec::{put_u32, put_f64, ByteReader};
+use crate::dataset::Dataset;
+
+pub struct StrictArg;
+
+impl BoolParser<'_> for StrictArg {
+    const NAMES: &'static [&'static str] = &["--strict"];
+    const DESCRIPTION: &'static str = "Refuses to predict outside the training range, exits with an error code if any input was refused";
+}
+
+/// Levels of the quantiles saved for each feature
+pub const QUANTILE_LEVELS: [f64; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+
+/// The values one feature took in the training rows
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureRange {
+    pub min: f64,
+    pub max: f64,
+    /// One per level of [TrainingRange::levels], linearly interpolated between rows
+    pub quantiles: Vec<f64>,
+}
+
+/// Range of each feature in the training rows. Features are checked one by one,
+/// a combination of in range values can still be far from every training row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingRange {
+    pub levels: Vec<f64>,
+    pub features: Vec<FeatureRange>,
+}
+
+impl TrainingRange {
+    /// None without rows
+    pub fn compute(dataset: &Dataset) -> Option<Self> {
+        if dataset.entries.is_empty() {
+            return None;
+        }
+        let features = (0..dataset.features.len()).map(|col| {
+            let mut values: Vec<_> = dataset.entries.iter().map(|it| it.features[col]).collect();
+            values.sort_unstable_by(f64::total_cmp);
+            let quantile = |level: f64| {
+                let position = level * (values.len() - 1) as f64;
+                let (below, above) = (position.floor() as usize, position.ceil() as usize);
+                values[below] + (values[above] - values[below]) * (position - below as f64)
+            };
+            FeatureRange { min: values[0], max: values[values.len() - 1], quantiles: QUANTILE_LEVELS.iter().map(|it| quantile(*it)).collect() }
+        }).collect();
+        Some(Self { levels: QUANTILE_LEVELS.to_vec(), features })
+    }
+
+    /// Describes each feature of `features` outside the training range, empty when interpolating
+    pub fn outside(&self, names: &[String], features: &[f64]) -> Vec<String> {
+        names.iter().zip(features).zip(&self.features).filter_map(|((name, value), range)| {
+            if *value < range.min {
+                Some(format!("{} {:.3} is below the training min {:.3}", name, value, range.min))
+            } else if *value > range.max {
+                Some(format!("{} {:.3} is above the training max {:.3}", name, value, range.max))
+            } else {
+                None
+            }
+        }).collect()
+    }
+
+    /// Describes each in range feature of `features` beyond the outer quantiles, where training rows are sparse
+    pub fn sparse(&self, names: &[String], features: &[f64]) -> Vec<String> {
+        let (first, last) = match (self.levels.first(), self.levels.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return vec![],
+        };
+        names.iter().zip(features).zip(&self.features).filter_map(|((name, value), range)| {
+            let (low, high) = (range.quantiles[0], range.quantiles[range.quantiles.len() - 1]);
+            if *value >= range.min && *value < low {
+                Some(format!("{} {:.3} is below the {}th percentile {:.3}", name, value, (first * 100.0).round(), low))
+            } else if *value <= range.max && *value > high {
+                Some(format!("{} {:.3} is above the {}th percentile {:.3}", name, value, (last * 100.0).round(), high))
+            } else {
+                None
+            }
+        }).collect()
+    }
+
+    /// Section payload: u32 level count, the levels as f64, then min, max and the quantiles of each feature as f64
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        put_u32(out, self.levels.len() as u32);
+        self.levels.iter().for_each(|it| put_f64(out, *it));
+        for feature in &self.features {
+            put_f64(out, feature.min);
+            put_f64(out, feature.max);
+            feature.quantiles.iter().for_each(|it| put_f64(out, *it));
+        }
+    }
+
+    /// The feature count is not in the payload, it is the model's
+    pub(crate) fn decode(reader: &mut ByteReader, features: usize) -> Result<Self, String> {
+        let count = reader.u32("quantile level count")? as usize;
+        let levels = (0..count).map(|idx| reader.f64(&format!("quantile level {}", idx))).collect::<Result<_, _>>()?;
+        let features = (0..features).map(|idx| {
+            Ok(FeatureRange {
+                min: reader.f64(&format!("feature {} min", idx))?,
+                max: reader.f64(&format!("feature {} max", idx))?,
+                quantiles: (0..count).map(|level| reader.f64(&format!("feature {} quantile {}", idx, level))).collect::<Result<_, String>>()?,
+            })
+        }).collect::<Result<_, String>>()?;
+        Ok(Self { levels, features })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::DatasetEntry;
+
+    fn range() -> TrainingRange {
+        let dataset = Dataset {
+            features: vec!["km".into(), "age".into()],
+            entries: (0..=20).map(|idx| DatasetEntry { features: vec![idx as f64 * 1000.0, 4.0], price: 0.0 }).collect(),
+        };
+        TrainingRange::compute(&dataset).unwrap()
+    }
+
+    #[test]
+    fn quantiles_interpolate() {
+        let range = range();
+        assert_eq!((range.features[0].min, range.features[0].max), (0.0, 20000.0));
+        assert_eq!(range.features[0].quantiles, vec![1000.0, 5000.0, 10000.0, 15000.0, 19000.0]);
+        assert_eq!(range.features[1].quantiles, vec![4.0; 5]);
+        assert!(TrainingRange::compute(&Dataset { features: vec!["km".into()], entries: vec![] }).is_none());
+    }
+
+    #[test]
+    fn outside_and_sparse() {
+        let (range, names) = (range(), vec!["km".to_string(), "age".to_string()]);
+        assert!(range.outside(&names, &[10000.0, 4.0]).is_empty());
+        assert_eq!(range.outside(&names, &[-1.0, 5.0]).len(), 2);
+        assert!(range.sparse(&names, &[10000.0, 4.0]).is_empty());
+        assert_eq!(range.sparse(&names, &[500.0, 4.0]), vec!["km 500.000 is below the 5th percentile 1000.000"]);
+        assert_eq!(range.sparse(&names, &[19500.0, 4.0]), vec!["km 19500.000 is above the 95th percentile 19000.000"]);
+        // out of range values are reported by outside only
+        assert!(range.sparse(&names, &[25000.0, 4.0]).is_empty());
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let range = range();
+        let mut payload = vec![];
+        range.encode(&mut payload);
+        assert_eq!(TrainingRange::decode(&mut ByteReader::new(&payload), 2), Ok(range));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::args::{ArgParser, DefaultArgParser};
+use crate::codec::{put_u8, put_u32, put_f64, ByteReader};
+use crate::dataset::{Dataset, DatasetEntry};
+
+pub struct ScalerArg;
+
+impl<'a> ArgParser<'a, ScalerKind> for ScalerArg {
+    const NAMES: &'static [&'static str] = &["-s", "--scaler"];
+    const VALUES: &'static [&'static str] = &["minmax", "zscore", "none"];
+    const DESCRIPTION: &'static str = "How the dataset is normalized before training";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<ScalerKind, String> {
+        match value {
+            Some("minmax") => Ok(ScalerKind::MinMax),
+            Some("zscore") => Ok(ScalerKind::ZScore),
+            Some("none") => Ok(ScalerKind::Identity),
+            Some(value) => Err(format!("Invalid value \"{}\", must be one of {}", value, Self::VALUES.join(", "))),
+            None => Err("Arg value is not optional, --help for more info".into()),
+        }
+    }
+}
+
+impl DefaultArgParser<'_, ScalerKind> for ScalerArg {
+    const DEFAULT: ScalerKind = ScalerKind::MinMax;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ScalerKind {
+    /// Maps each column onto [0, 1]
+    MinMax,
+    /// Centers each column on its mean with unit standard deviation
+    ZScore,
+    /// Leaves values untouched
+    Identity,
+}
+
+impl ScalerKind {
+    fn id(self) -> u8 {
+        match self {
+            ScalerKind::Identity => 0,
+            ScalerKind::MinMax => 1,
+            ScalerKind::ZScore => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, String> {
+        match id {
+            0 => Ok(ScalerKind::Identity),
+            1 => Ok(ScalerKind::MinMax),
+            2 => Ok(ScalerKind::ZScore),
+            _ => Err(format!("unknown scaler kind {}", id)),
+        }
+    }
+}
+
+/// Affine mapping of one column: `scaled = (value - offset) / scale`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Scale {
+    pub offset: f64,
+    pub scale: f64,
+}
+
+impl Scale {
+    const IDENTITY: Scale = Scale { offset: 0.0, scale: 1.0 };
+
+    fn fit(kind: ScalerKind, values: impl Iterator<Item=f64> + Clone) -> Self {
+        let scale = match kind {
+            ScalerKind::Identity => return Self::IDENTITY,
+            ScalerKind::MinMax => {
+                let (min, max) = values.fold((f64::MAX, f64::MIN), |(min, max), it| (min.min(it), max.max(it)));
+                Scale { offset: min, scale: max - min }
+            }
+            ScalerKind::ZScore => {
+                let (count, sum) = values.clone().fold((0usize, 0.0), |(count, sum), it| (count + 1, sum + it));
+                let mean = sum / count as f64;
+                let variance = values.map(|it| (it - mean) * (it - mean)).sum::<f64>() / count as f64;
+                Scale { offset: mean, scale: variance.sqrt() }
+            }
+        };
+        // an empty column has nothing to fit, a constant one would divide by zero so it is only centered
+        if !scale.scale.is_finite() || !scale.offset.is_finite() {
+            Self::IDENTITY
+        } else if scale.scale == 0.0 {
+            Scale { offset: scale.offset, scale: 1.0 }
+        } else {
+            scale
+        }
+    }
+
+    #[inline]
+    pub fn transform(&self, value: f64) -> f64 {
+        (value - self.offset) / self.scale
+    }
+
+    #[inline]
+    pub fn inverse_transform(&self, value: f64) -> f64 {
+        value * self.scale + self.offset
+    }
+}
+
+/// Normalization fitted once on the training data and saved with the model,
+/// so predictions can be made in the same space without the original dataset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scaler {
+    pub kind: ScalerKind,
+    /// One scale per feature, in dataset order
+    pub features: Vec<Scale>,
+    pub price: Scale,
+}
+
+impl Scaler {
+    pub fn fit(kind: ScalerKind, dataset: &Dataset) -> Self {
+        Self {
+            kind,
+            features: (0..dataset.features.len()).map(|idx| Scale::fit(kind, dataset.entries.iter().map(|it| it.features[idx]))).collect(),
+            price: Scale::fit(kind, dataset.entries.iter().map(|it| it.price)),
+        }
+    }
+
+    pub fn transform_features(&self, features: &[f64]) -> Vec<f64> {
+        features.iter().zip(&self.features).map(|(value, scale)| scale.transform(*value)).collect()
+    }
+
+    pub fn inverse_transform_features(&self, features: &[f64]) -> Vec<f64> {
+        features.iter().zip(&self.features).map(|(value, scale)| scale.inverse_transform(*value)).collect()
+    }
+
+    pub fn transform_entry(&self, entry: &DatasetEntry) -> DatasetEntry {
+        DatasetEntry {
+            features: self.transform_features(&entry.features),
+            price: self.price.transform(entry.price),
+        }
+    }
+
+    pub fn inverse_transform_entry(&self, entry: &DatasetEntry) -> DatasetEntry {
+        DatasetEntry {
+            features: self.inverse_transform_features(&entry.features),
+            price: self.price.inverse_transform(entry.price),
+        }
+    }
+
+    pub fn transform(&self, dataset: &Dataset) -> Dataset {
+        Dataset { features: dataset.features.clone(), entries: dataset.entries.iter().map(|it| self.transform_entry(it)).collect() }
+    }
+
+    pub fn inverse_transform(&self, dataset: &Dataset) -> Dataset {
+        Dataset { features: dataset.features.clone(), entries: dataset.entries.iter().map(|it| self.inverse_transform_entry(it)).collect() }
+    }
+
+    /// Converts coefficients fitted on raw values into the scaled space
+    pub fn transform_theta(&self, theta: &[f64]) -> Vec<f64> {
+        let shift = self.features.iter().zip(&theta[1..]).map(|(scale, theta)| theta * scale.offset).sum::<f64>();
+        let mut scaled = vec![(theta[0] - self.price.offset + shift) / self.price.scale];
+        scaled.extend(self.features.iter().zip(&theta[1..]).map(|(scale, theta)| theta * scale.scale / self.price.scale));
+        scaled
+    }
+
+    /// Converts coefficients fitted in the scaled space back to raw values
+    pub fn inverse_transform_theta(&self, theta: &[f64]) -> Vec<f64> {
+        let mut raw = vec![0.0];
+        raw.extend(self.features.iter().zip(&theta[1..]).map(|(scale, theta)| theta / scale.scale * self.price.scale));
+        raw[0] = theta[0] * self.price.scale + self.price.offset - self.features.iter().zip(&raw[1..]).map(|(scale, theta)| scale.offset * theta).sum::<f64>();
+        raw
+    }
+
+    /// Section payload: kind as u8, column count as u32, then offset and scale of each column, the price last
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        put_u8(out, self.kind.id());
+        put_u32(out, self.features.len() as u32 + 1);
+        for column in self.features.iter().chain(Some(&self.price)) {
+            put_f64(out, column.offset);
+            put_f64(out, column.scale);
+        }
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        let kind = ScalerKind::from_id(reader.u8("scaler kind")?)?;
+        let columns = reader.u32("scaler column count")? as usize;
+        if columns < 2 {
+            return Err(format!("scaler has {} columns, expected at least 2", columns));
+        }
+        let mut columns = (0..columns).map(|idx| -> Result<Scale, String> {
+            Ok(Scale {
+                offset: reader.f64(&format!("column {} scale offset", idx))?,
+                scale: reader.f64(&format!("column {} scale", idx))?,
+            })
+        }).collect::<Result<Vec<_>, _>>()?;
+        let price = columns.pop().unwrap();
+        Ok(Self {
+            kind,
+            features: columns,
+            price,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataset() -> Dataset {
+        Dataset {
+            features: vec!["km".into(), "age".into()],
+            entries: vec![
+                DatasetEntry { features: vec![240000.0, 12.0], price: 3650.0 },
+                DatasetEntry { features: vec![139800.0, 8.0], price: 3800.0 },
+                DatasetEntry { features: vec![22899.0, 1.0], price: 7990.0 },
+                DatasetEntry { features: vec![84000.0, 5.0], price: 6200.0 },
+            ],
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+    }
+
+    #[test]
+    fn round_trip() {
+        let dataset = dataset();
+        for kind in [ScalerKind::MinMax, ScalerKind::ZScore, ScalerKind::Identity] {
+            let scaler = Scaler::fit(kind, &dataset);
+            let restored = scaler.inverse_transform(&scaler.transform(&dataset));
+            for (entry, restored) in dataset.entries.iter().zip(&restored.entries) {
+                assert!(close(entry.price, restored.price), "{:?}", kind);
+                assert!(entry.features.iter().zip(&restored.features).all(|(a, b)| close(*a, *b)), "{:?}", kind);
+            }
+            let theta = [8499.6, -0.0214, -35.0];
+            let restored = scaler.inverse_transform_theta(&scaler.transform_theta(&theta));
+            assert!(theta.iter().zip(&restored).all(|(a, b)| close(*a, *b)), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn min_max_bounds() {
+        let scaled = Scaler::fit(ScalerKind::MinMax, &dataset()).transform(&dataset());
+        let min = scaled.entries.iter().map(|it| it.features[0]).fold(f64::MAX, f64::min);
+        let max = scaled.entries.iter().map(|it| it.features[0]).fold(f64::MIN, f64::max);
+        assert_eq!((min, max), (0.0, 1.0));
+    }
+
+    #[test]
+    fn constant_column_is_only_centered() {
+        let dataset = Dataset {
+            features: vec!["km".into()],
+            entries: vec![DatasetEntry { features: vec![5.0], price: 1.0 }, DatasetEntry { features: vec![5.0], price: 2.0 }],
+        };
+        let scaler = Scaler::fit(ScalerKind::ZScore, &dataset);
+        assert_eq!(scaler.features, vec![Scale { offset: 5.0, scale: 1.0 }]);
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let scaler = Scaler::fit(ScalerKind::ZScore, &dataset());
+        let mut bytes = vec![];
+        scaler.encode(&mut bytes);
+        let mut reader = ByteReader::new(&bytes);
+        assert_eq!(Scaler::decode(&mut reader), Ok(scaler));
+        assert!(reader.is_empty());
+    }
+}
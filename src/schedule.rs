@@ -0,0 +1,357 @@
+use crate::args::{ArgParser, DefaultArgParser, F64Parser, UsizeParser, BoolParser};
+use crate::codec::{put_u8, put_u64, put_f64, ByteReader};
+use crate::convergence::{StopCriteria, StopReason};
+use crate::dataset::Dataset;
+use crate::estimate_price::estimate_price;
+use crate::solver::{gradient_descent, GdParams, Start};
+use crate::history::History;
+use crate::penalty::Penalty;
+use std::f64::consts::PI;
+
+pub struct ScheduleArg;
+
+impl<'a> ArgParser<'a, ScheduleName> for ScheduleArg {
+    const NAMES: &'static [&'static str] = &["--schedule"];
+    const VALUES: &'static [&'static str] = &["constant", "step", "exponential", "cosine", "backtracking"];
+    const DESCRIPTION: &'static str = "How the learning ratio changes during training";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<ScheduleName, String> {
+        match value {
+            Some("constant") => Ok(ScheduleName::Constant),
+            Some("step") => Ok(ScheduleName::Step),
+            Some("exponential") => Ok(ScheduleName::Exponential),
+            Some("cosine") => Ok(ScheduleName::Cosine),
+            Some("backtracking") => Ok(ScheduleName::Backtracking),
+            Some(value) => Err(format!("Invalid value \"{}\", must be one of {}", value, Self::VALUES.join(", "))),
+            None => Err("Arg value is not optional, --help for more info".into()),
+        }
+    }
+}
+
+impl DefaultArgParser<'_, ScheduleName> for ScheduleArg {
+    const DEFAULT: ScheduleName = ScheduleName::Constant;
+}
+
+pub struct DecayArg;
+
+impl F64Parser<'_> for DecayArg {
+    const NAMES: &'static [&'static str] = &["--decay"];
+    const DESCRIPTION: &'static str = "Ratio multiplier, per step for step decay (default 0.5) or per iteration for exponential decay (default 0.999)";
+}
+
+pub struct DecayEveryArg;
+
+impl UsizeParser<'_> for DecayEveryArg {
+    const NAMES: &'static [&'static str] = &["--decay-every"];
+    const DESCRIPTION: &'static str = "Iterations between two steps of step decay";
+}
+
+impl DefaultArgParser<'_, usize> for DecayEveryArg {
+    const DEFAULT: usize = 1000;
+}
+
+pub struct PeriodArg;
+
+impl UsizeParser<'_> for PeriodArg {
+    const NAMES: &'static [&'static str] = &["--period"];
+    const DESCRIPTION: &'static str = "Iterations cosine annealing takes to reach the minimum ratio";
+}
+
+impl DefaultArgParser<'_, usize> for PeriodArg {
+    const DEFAULT: usize = 1000;
+}
+
+pub struct MinRatioArg;
+
+impl F64Parser<'_> for MinRatioArg {
+    const NAMES: &'static [&'static str] = &["--min-ratio"];
+    const DESCRIPTION: &'static str = "Ratio cosine annealing ends at";
+}
+
+impl DefaultArgParser<'_, f64> for MinRatioArg {
+    const DEFAULT: f64 = 0.0;
+}
+
+pub struct ShrinkArg;
+
+impl F64Parser<'_> for ShrinkArg {
+    const NAMES: &'static [&'static str] = &["--shrink"];
+    const DESCRIPTION: &'static str = "Ratio multiplier of each backtracking attempt";
+
+    fn check(value: f64) -> Result<(), String> {
+        if value > 0.0 && value < 1.0 { Ok(()) } else { Err("Shrink must be 0 < S < 1, ignoring".into()) }
+    }
+}
+
+impl DefaultArgParser<'_, f64> for ShrinkArg {
+    const DEFAULT: f64 = 0.5;
+}
+
+pub struct ArmijoArg;
+
+impl F64Parser<'_> for ArmijoArg {
+    const NAMES: &'static [&'static str] = &["--armijo"];
+    const DESCRIPTION: &'static str = "Fraction of the expected decrease backtracking requires";
+
+    fn check(value: f64) -> Result<(), String> {
+        if value > 0.0 && value < 1.0 { Ok(()) } else { Err("Armijo fraction must be 0 < A < 1, ignoring".into()) }
+    }
+}
+
+impl DefaultArgParser<'_, f64> for ArmijoArg {
+    const DEFAULT: f64 = 1e-4;
+}
+
+pub struct FindRatioArg;
+
+impl BoolParser<'_> for FindRatioArg {
+    const NAMES: &'static [&'static str] = &["--find-ratio"];
+    const DESCRIPTION: &'static str = "Sweep learning ratios, recommend one and train with it";
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ScheduleName {
+    Constant,
+    Step,
+    Exponential,
+    Cosine,
+    Backtracking,
+}
+
+/// How the learning ratio evolves, `t` counting iterations, or epochs in mini-batch mode
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum Schedule {
+    #[default]
+    Constant,
+    /// ratio × factor^⌊t / every⌋
+    Step { every: usize, factor: f64 },
+    /// ratio × factor^t
+    Exponential { factor: f64 },
+    /// From ratio down to min_ratio along half a cosine over `period`, then min_ratio
+    Cosine { period: usize, min_ratio: f64 },
+    /// Armijo line search: each iteration starts from ratio and shrinks it until the cost decreases enough
+    Backtracking { shrink: f64, armijo: f64 },
+}
+
+impl Schedule {
+    /// Reads the schedule and only the parameters it uses, the others are left unused so they get reported
+    pub fn parse(input: &[String], used: &mut [bool]) -> Self {
+        match ScheduleArg::parse(input, used) {
+            ScheduleName::Constant => Schedule::Constant,
+            ScheduleName::Step => Schedule::Step { every: DecayEveryArg::parse(input, used).max(1), factor: DecayArg::try_parse(input, used).unwrap_or(0.5) },
+            ScheduleName::Exponential => Schedule::Exponential { factor: DecayArg::try_parse(input, used).unwrap_or(0.999) },
+            ScheduleName::Cosine => Schedule::Cosine { period: PeriodArg::parse(input, used).max(1), min_ratio: MinRatioArg::parse(input, used) },
+            ScheduleName::Backtracking => Schedule::Backtracking { shrink: ShrinkArg::parse(input, used), armijo: ArmijoArg::parse(input, used) },
+        }
+    }
+
+    /// The ratio of step `t`, backtracking only gives its starting point
+    pub fn ratio(&self, ratio: f64, t: usize) -> f64 {
+        match *self {
+            Schedule::Constant | Schedule::Backtracking { .. } => ratio,
+            Schedule::Step { every, factor } => ratio * factor.powi((t / every).min(i32::MAX as usize) as i32),
+            Schedule::Exponential { factor } => ratio * factor.powf(t as f64),
+            Schedule::Cosine { period, min_ratio } => {
+                let progress = t.min(period) as f64 / period as f64;
+                min_ratio + (ratio - min_ratio) * (1.0 + (PI * progress).cos()) / 2.0
+            }
+        }
+    }
+
+    /// Kind as u8, its step count as u64, then its two factors as f64, unused ones being 0
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        let (id, steps, first, second) = match *self {
+            Schedule::Constant => (0, 0, 0.0, 0.0),
+            Schedule::Step { every, factor } => (1, every, factor, 0.0),
+            Schedule::Exponential { factor } => (2, 0, factor, 0.0),
+            Schedule::Cosine { period, min_ratio } => (3, period, min_ratio, 0.0),
+            Schedule::Backtracking { shrink, armijo } => (4, 0, shrink, armijo),
+        };
+        put_u8(out, id);
+        put_u64(out, steps as u64);
+        put_f64(out, first);
+        put_f64(out, second);
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        let id = reader.u8("schedule kind")?;
+        let steps = reader.u64("schedule steps")? as usize;
+        let first = reader.f64("schedule factor")?;
+        let second = reader.f64("schedule factor")?;
+        match id {
+            0 => Ok(Schedule::Constant),
+            1 => Ok(Schedule::Step { every: steps.max(1), factor: first }),
+            2 => Ok(Schedule::Exponential { factor: first }),
+            3 => Ok(Schedule::Cosine { period: steps.max(1), min_ratio: first }),
+            4 => Ok(Schedule::Backtracking { shrink: first, armijo: second }),
+            _ => Err(format!("unknown schedule kind {}", id)),
+        }
+    }
+}
+
+/// Half the mean squared error, the function the gradient is taken of
+fn half_cost(dataset: &Dataset, theta: &[f64]) -> f64 {
+    dataset.entries.iter().map(|it| {
+        let error = estimate_price(&it.features, theta) - it.price;
+        error * error
+    }).sum::<f64>() / (2 * dataset.entries.len()) as f64
+}
+
+/// Shrinks `ratio` until a plain gradient step satisfies the Armijo condition on the cost plus `penalty`,
+/// `cost` being the mean squared error at theta and `gradient` that of both.
+/// Gives up under 1e-30, where the step would not change theta anyway.
+pub fn backtrack(dataset: &Dataset, penalty: &Penalty, theta: &[f64], gradient: &[f64], cost: f64, mut ratio: f64, (shrink, armijo): (f64, f64)) -> f64 {
+    let current = cost / 2.0 + penalty.value(theta);
+    let slope = gradient.iter().map(|it| it * it).sum::<f64>();
+    let mut candidate = theta.to_vec();
+    while ratio > 1e-30 {
+        candidate.iter_mut().zip(theta).zip(gradient).for_each(|((candidate, theta), gradient)| *candidate = theta - ratio * gradient);
+        if half_cost(dataset, &candidate) + penalty.value(&candidate) <= current - armijo * ratio * slope {
+            break;
+        }
+        ratio *= shrink;
+    }
+    ratio
+}
+
+/// Runs a short training for log-spaced ratios between `min` and `max`, with the optimizer, schedule and batching of `params`.
+/// Returns each ratio with the cost it reached, infinite when it diverged.
+pub fn lr_range_test(dataset: &Dataset, params: &GdParams, min: f64, max: f64, per_decade: usize, iterations: usize) -> Vec<(f64, f64)> {
+    let steps = ((max / min).log10() * per_decade as f64).round() as usize;
+    (0..=steps).map(|step| {
+        let ratio = min * 10f64.powf(step as f64 / per_decade as f64);
+        let params = GdParams {
+            ratio,
+            criteria: StopCriteria { max_iterations: Some(iterations), ..StopCriteria::default() },
+            checkpoint_every: None,
+            verbose: false,
+            ..params.clone()
+        };
+        let (theta, summary) = gradient_descent(dataset, &params, Start::Zero, &mut History::default(), &mut |_, _| {});
+        let cost = if summary.stop == Some(StopReason::Diverged) { f64::INFINITY } else { half_cost(dataset, &theta) * 2.0 };
+        (ratio, if cost.is_finite() { cost } else { f64::INFINITY })
+    }).collect()
+}
+
+/// Half the ratio that got lowest, the best ratio of a short run often sits right before divergence
+pub fn recommend_ratio(sweep: &[(f64, f64)]) -> Option<f64> {
+    sweep.iter().filter(|(_, cost)| cost.is_finite()).fold(None, |best: Option<(f64, f64)>, &(ratio, cost)| {
+        match best {
+            Some((_, best_cost)) if best_cost <= cost => best,
+            _ => Some((ratio, cost)),
+        }
+    }).map(|(ratio, _)| ratio / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::DatasetEntry;
+    use crate::optimizer::OptimizerKind;
+    use crate::solver::ols;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-12 * a.abs().max(b.abs()).max(1.0)
+    }
+
+    /// price = 1 + 2 x exactly
+    fn linear() -> Dataset {
+        Dataset {
+            features: vec!["x".into()],
+            entries: [0.0, 0.25, 0.5, 0.75, 1.0].iter().map(|&x| DatasetEntry { features: vec![x], price: 1.0 + 2.0 * x }).collect(),
+        }
+    }
+
+    fn params(ratio: f64, schedule: Schedule) -> GdParams {
+        GdParams { ratio, criteria: StopCriteria::default(), optimizer: OptimizerKind::Vanilla, schedule, penalty: Penalty::None, batching: None, checkpoint_every: None, verbose: false }
+    }
+
+    #[test]
+    fn backtracking_fractions_are_checked() {
+        assert_eq!(ShrinkArg::parse_arg_value(Some("0.5")), Ok(0.5));
+        assert!(ShrinkArg::parse_arg_value(Some("1")).is_err());
+        assert!(ArmijoArg::parse_arg_value(Some("0")).is_err());
+        assert_eq!(ArmijoArg::parse_arg_value(Some("1e-4")), Ok(1e-4));
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let schedules = [
+            Schedule::Constant,
+            Schedule::Step { every: 10, factor: 0.5 },
+            Schedule::Exponential { factor: 0.99 },
+            Schedule::Cosine { period: 100, min_ratio: 1e-3 },
+            Schedule::Backtracking { shrink: 0.5, armijo: 1e-4 },
+        ];
+        for schedule in schedules {
+            let mut bytes = vec![];
+            schedule.encode(&mut bytes);
+            assert_eq!(Schedule::decode(&mut crate::codec::ByteReader::new(&bytes)), Ok(schedule));
+        }
+    }
+
+    #[test]
+    fn ratios() {
+        let step = Schedule::Step { every: 10, factor: 0.5 };
+        assert_eq!((step.ratio(1.0, 9), step.ratio(1.0, 10), step.ratio(1.0, 25)), (1.0, 0.5, 0.25));
+        assert!(close(Schedule::Exponential { factor: 0.9 }.ratio(2.0, 2), 1.62));
+        let cosine = Schedule::Cosine { period: 100, min_ratio: 0.1 };
+        assert!(close(cosine.ratio(1.0, 0), 1.0));
+        assert!(close(cosine.ratio(1.0, 50), 0.55));
+        assert!(close(cosine.ratio(1.0, 100), 0.1) && close(cosine.ratio(1.0, 1000), 0.1));
+        assert_eq!(Schedule::Backtracking { shrink: 0.5, armijo: 1e-4 }.ratio(0.3, 1000), 0.3);
+    }
+
+    #[test]
+    fn backtrack_satisfies_armijo() {
+        let dataset = linear();
+        let theta = [0.0, 0.0];
+        let gradient = [-3.0, -1.875];
+        let cost = half_cost(&dataset, &theta) * 2.0;
+        let ratio = backtrack(&dataset, &Penalty::None, &theta, &gradient, cost, 100.0, (0.5, 0.5));
+        assert!(ratio < 100.0);
+        let step: Vec<_> = theta.iter().zip(&gradient).map(|(theta, gradient)| theta - ratio * gradient).collect();
+        let slope = gradient.iter().map(|it| it * it).sum::<f64>();
+        assert!(half_cost(&dataset, &step) <= cost / 2.0 - 0.5 * ratio * slope);
+        // the ratio before the last shrink failed the condition
+        let step: Vec<_> = theta.iter().zip(&gradient).map(|(theta, gradient)| theta - ratio * 2.0 * gradient).collect();
+        assert!(half_cost(&dataset, &step) > cost / 2.0 - 0.5 * ratio * 2.0 * slope);
+    }
+
+    #[test]
+    fn backtracking_reaches_ols() {
+        let dataset = linear();
+        let (theta, _) = gradient_descent(&dataset, &params(10.0, Schedule::Backtracking { shrink: 0.5, armijo: 1e-4 }), Start::Zero, &mut History::default(), &mut |_, _| {});
+        let exact = ols(&dataset, Penalty::None).unwrap();
+        assert!(theta.iter().zip(&exact).all(|(a, b)| (a - b).abs() < 1e-9), "{:?} != {:?}", theta, exact);
+    }
+
+    #[test]
+    fn backtracking_accounts_for_the_penalty() {
+        let dataset = linear();
+        let penalty = Penalty::Ridge { lambda: 10.0 };
+        let theta = [1.0, 2.0];
+        // the fit is exact, only the penalty pulls theta
+        let gradient = [0.0, 20.0];
+        let ratio = backtrack(&dataset, &penalty, &theta, &gradient, 0.0, 1.0, (0.5, 0.5));
+        let step = [1.0, 2.0 - ratio * 20.0];
+        assert!(half_cost(&dataset, &step) + penalty.value(&step) <= penalty.value(&theta) - 0.5 * ratio * 400.0);
+        assert!(ratio < 1.0);
+
+        let params = GdParams { penalty, ..params(10.0, Schedule::Backtracking { shrink: 0.5, armijo: 1e-4 }) };
+        let (theta, _) = gradient_descent(&dataset, &params, Start::Zero, &mut History::default(), &mut |_, _| {});
+        let exact = ols(&dataset, penalty).unwrap();
+        assert!(theta.iter().zip(&exact).all(|(a, b)| (a - b).abs() < 1e-6), "{:?} != {:?}", theta, exact);
+    }
+
+    #[test]
+    fn range_test_recommends_a_converging_ratio() {
+        let dataset = linear();
+        let sweep = lr_range_test(&dataset, &params(0.1, Schedule::Constant), 1e-3, 1e3, 2, 100);
+        assert_eq!(sweep.len(), 13);
+        assert!(sweep.last().unwrap().1.is_infinite());
+        let ratio = recommend_ratio(&sweep).unwrap();
+        assert!(ratio > 1e-3 && ratio < 2.0, "{}", ratio);
+        assert_eq!(recommend_ratio(&[(1.0, f64::INFINITY)]), None);
+    }
+}
@@ -0,0 +1,342 @@
+use crate::args::{ArgParser, DefaultArgParser, UsizeParser};
+use crate::penalty::Penalty;
+use crate::random::Random;
+use crate::solver::{GdParams, Batching};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+pub struct SearchArg;
+
+impl<'a> ArgParser<'a, SearchKind> for SearchArg {
+    const NAMES: &'static [&'static str] = &["--search"];
+    const VALUES: &'static [&'static str] = &["grid", "random"];
+    const DESCRIPTION: &'static str = "Tries every combination of the given values, or random ones within them";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<SearchKind, String> {
+        match value {
+            Some("grid") => Ok(SearchKind::Grid),
+            Some("random") => Ok(SearchKind::Random),
+            Some(value) => Err(format!("Invalid value \"{}\", must be one of {}", value, Self::VALUES.join(", "))),
+            None => Err("Arg value is not optional, --help for more info".into()),
+        }
+    }
+}
+
+impl DefaultArgParser<'_, SearchKind> for SearchArg {
+    const DEFAULT: SearchKind = SearchKind::Grid;
+}
+
+pub struct TrialsArg;
+
+impl UsizeParser<'_> for TrialsArg {
+    const NAMES: &'static [&'static str] = &["--trials"];
+    const DESCRIPTION: &'static str = "Candidates a random search draws";
+}
+
+impl DefaultArgParser<'_, usize> for TrialsArg {
+    const DEFAULT: usize = 20;
+}
+
+pub struct ThreadsArg;
+
+impl UsizeParser<'_> for ThreadsArg {
+    const NAMES: &'static [&'static str] = &["--threads"];
+    const DESCRIPTION: &'static str = "Candidates evaluated at once, defaults to the available parallelism";
+}
+
+pub struct SearchSeedArg;
+
+impl UsizeParser<'_> for SearchSeedArg {
+    const NAMES: &'static [&'static str] = &["--search-seed"];
+    const DESCRIPTION: &'static str = "Seed of the random search, taken from the clock when not set";
+}
+
+/// Values of a hyperparameter: `a,b,c` or `min..max:count`
+const VALUES: &[&str] = &["<a,b,...>", "<min..max:count>"];
+
+pub struct RatiosArg;
+
+impl<'a> ArgParser<'a, Values> for RatiosArg {
+    const NAMES: &'static [&'static str] = &["--ratios"];
+    const VALUES: &'static [&'static str] = VALUES;
+    const DESCRIPTION: &'static str = "Learning ratios to try, ranges are log spaced";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<Values, String> {
+        Values::parse(value)
+    }
+}
+
+pub struct LambdasArg;
+
+impl<'a> ArgParser<'a, Values> for LambdasArg {
+    const NAMES: &'static [&'static str] = &["--lambdas"];
+    const VALUES: &'static [&'static str] = VALUES;
+    const DESCRIPTION: &'static str = "Penalty strengths to try, ranges are log spaced";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<Values, String> {
+        Values::parse(value)
+    }
+}
+
+pub struct L1RatiosArg;
+
+impl<'a> ArgParser<'a, Values> for L1RatiosArg {
+    const NAMES: &'static [&'static str] = &["--l1-ratios"];
+    const VALUES: &'static [&'static str] = VALUES;
+    const DESCRIPTION: &'static str = "Elastic net L1 shares to try, ranges are linear";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<Values, String> {
+        Values::parse(value)
+    }
+}
+
+pub struct BatchSizesArg;
+
+impl<'a> ArgParser<'a, Values> for BatchSizesArg {
+    const NAMES: &'static [&'static str] = &["--batch-sizes"];
+    const VALUES: &'static [&'static str] = VALUES;
+    const DESCRIPTION: &'static str = "Mini-batch sizes to try, ranges are log spaced and rounded";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<Values, String> {
+        Values::parse(value)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SearchKind {
+    Grid,
+    Random,
+}
+
+/// The values a hyperparameter may take
+#[derive(Clone, Debug, PartialEq)]
+pub enum Values {
+    List(Vec<f64>),
+    /// `count` values from `min` to `max` in a grid, anything between them in a random search
+    Range { min: f64, max: f64, count: usize },
+}
+
+impl Values {
+    fn parse(value: Option<&str>) -> Result<Self, String> {
+        let value = value.ok_or_else(|| "Arg value is not optional, --help for more info".to_string())?;
+        let float = |it: &str| f64::from_str(it.trim()).map_err(|_| format!("\"{}\" is not a <float>", it));
+        if let Some((min, rest)) = value.split_once("..") {
+            let (max, count) = rest.split_once(':').ok_or_else(|| format!("Range \"{}\" has no :count", value))?;
+            let count = usize::from_str(count.trim()).map_err(|_| format!("\"{}\" is not an <int>", count))?;
+            let (min, max) = (float(min)?, float(max)?);
+            if count == 0 || min > max {
+                return Err(format!("Range \"{}\" is empty", value));
+            }
+            Ok(Values::Range { min, max, count })
+        } else {
+            Ok(Values::List(value.split(',').map(float).collect::<Result<_, _>>()?))
+        }
+    }
+
+    /// Whether every value is positive, which log spacing needs
+    pub fn positive(&self) -> bool {
+        match self {
+            Values::List(values) => values.iter().all(|it| *it > 0.0),
+            Values::Range { min, .. } => *min > 0.0,
+        }
+    }
+
+    /// Every value of a grid search, ranges spaced evenly or evenly on a log scale
+    pub fn grid(&self, log: bool) -> Vec<f64> {
+        match self {
+            Values::List(values) => values.clone(),
+            Values::Range { min, max, count: 1 } => vec![if log { (min * max).sqrt() } else { (min + max) / 2.0 }],
+            &Values::Range { min, max, count } => (0..count).map(|idx| {
+                let t = idx as f64 / (count - 1) as f64;
+                if log { min * (max / min).powf(t) } else { min + (max - min) * t }
+            }).collect(),
+        }
+    }
+
+    /// A value of a random search: any of a list, anything in a range, uniformly or log-uniformly
+    pub fn sample(&self, log: bool, random: &mut Random) -> f64 {
+        match self {
+            Values::List(values) => values[random.below(values.len())],
+            &Values::Range { min, max, .. } => {
+                let t = random.next_f64();
+                if log { min * (max / min).powf(t) } else { min + (max - min) * t }
+            }
+        }
+    }
+}
+
+/// One combination of hyperparameters, unset ones keep the base value
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub ratio: f64,
+    pub lambda: Option<f64>,
+    pub l1_ratio: Option<f64>,
+    pub batch_size: Option<usize>,
+}
+
+impl Candidate {
+    /// `base` with this candidate's values. A batch size keeps the seed of the base batching, or gets `seed` on a full batch base.
+    pub fn apply(&self, base: &GdParams, seed: u64) -> GdParams {
+        let penalty = match base.penalty {
+            Penalty::None => Penalty::None,
+            Penalty::Ridge { lambda } => Penalty::Ridge { lambda: self.lambda.unwrap_or(lambda) },
+            Penalty::Lasso { lambda } => Penalty::Lasso { lambda: self.lambda.unwrap_or(lambda) },
+            Penalty::ElasticNet { lambda, l1_ratio } => Penalty::ElasticNet { lambda: self.lambda.unwrap_or(lambda), l1_ratio: self.l1_ratio.unwrap_or(l1_ratio) },
+        };
+        let batching = match (self.batch_size, base.batching) {
+            (Some(batch_size), Some(batching)) => Some(Batching { batch_size, ..batching }),
+            (Some(batch_size), None) => Some(Batching { batch_size, seed }),
+            (None, batching) => batching,
+        };
+        GdParams { ratio: self.ratio, penalty, batching, ..base.clone() }
+    }
+}
+
+impl Display for Candidate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ratio {:e}", self.ratio)?;
+        if let Some(lambda) = self.lambda {
+            write!(f, ", lambda {:e}", lambda)?;
+        }
+        if let Some(l1_ratio) = self.l1_ratio {
+            write!(f, ", l1 ratio {:.3}", l1_ratio)?;
+        }
+        if let Some(batch_size) = self.batch_size {
+            write!(f, ", batch size {}", batch_size)?;
+        }
+        Ok(())
+    }
+}
+
+/// The values each hyperparameter may take, those not searched are none
+#[derive(Clone, Debug, PartialEq)]
+pub struct Space {
+    pub ratios: Values,
+    pub lambdas: Option<Values>,
+    pub l1_ratios: Option<Values>,
+    pub batch_sizes: Option<Values>,
+}
+
+impl Space {
+    fn candidate(&self, mut pick: impl FnMut(&Values, bool) -> f64) -> Candidate {
+        Candidate {
+            ratio: pick(&self.ratios, true),
+            lambda: self.lambdas.as_ref().map(|it| pick(it, true)),
+            l1_ratio: self.l1_ratios.as_ref().map(|it| pick(it, false).clamp(0.0, 1.0)),
+            batch_size: self.batch_sizes.as_ref().map(|it| pick(it, true).round().max(1.0) as usize),
+        }
+    }
+
+    /// Every combination, duplicates left out
+    pub fn grid(&self) -> Vec<Candidate> {
+        let values = |it: Option<&Values>, log: bool| it.map_or(vec![f64::NAN], |it| it.grid(log));
+        let mut candidates: Vec<Candidate> = vec![];
+        for ratio in self.ratios.grid(true) {
+            for lambda in values(self.lambdas.as_ref(), true) {
+                for l1_ratio in values(self.l1_ratios.as_ref(), false) {
+                    for batch_size in values(self.batch_sizes.as_ref(), true) {
+                        let candidate = Candidate {
+                            ratio,
+                            lambda: Some(lambda).filter(|it| !it.is_nan()),
+                            l1_ratio: Some(l1_ratio).filter(|it| !it.is_nan()).map(|it| it.clamp(0.0, 1.0)),
+                            batch_size: Some(batch_size).filter(|it| !it.is_nan()).map(|it| it.round().max(1.0) as usize),
+                        };
+                        if !candidates.contains(&candidate) {
+                            candidates.push(candidate);
+                        }
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    /// `trials` random combinations
+    pub fn random(&self, trials: usize, random: &mut Random) -> Vec<Candidate> {
+        (0..trials).map(|_| self.candidate(|values, log| values.sample(log, random))).collect()
+    }
+}
+
+/// Maps `items` with `f` on `threads` threads, keeping their order. Each thread takes the next item as soon as it is done with one.
+pub fn parallel_map<T: Sync, R: Send>(items: &[T], threads: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                if idx >= items.len() {
+                    break;
+                }
+                let result = f(&items[idx]);
+                results.lock().unwrap()[idx] = Some(result);
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: &[f64], expected: &[f64]) -> bool {
+        actual.len() == expected.len() && actual.iter().zip(expected).all(|(a, e)| (a - e).abs() <= 1e-12 * e.abs().max(1.0))
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(Values::parse(Some("0.1, 0.2")), Ok(Values::List(vec![0.1, 0.2])));
+        assert_eq!(Values::parse(Some("1..100:3")), Ok(Values::Range { min: 1.0, max: 100.0, count: 3 }));
+        assert!(Values::parse(Some("1..100")).is_err());
+        assert!(Values::parse(Some("100..1:3")).is_err());
+        assert!(Values::parse(Some("1..100:0")).is_err());
+        assert!(Values::parse(None).is_err());
+        let range = Values::Range { min: 1.0, max: 100.0, count: 3 };
+        assert!(close(&range.grid(true), &[1.0, 10.0, 100.0]));
+        assert!(close(&range.grid(false), &[1.0, 50.5, 100.0]));
+        assert!(close(&Values::Range { min: 1.0, max: 100.0, count: 1 }.grid(true), &[10.0]));
+        assert!(!Values::List(vec![1.0, 0.0]).positive());
+    }
+
+    #[test]
+    fn grid_is_the_product_without_duplicates() {
+        let space = Space {
+            ratios: Values::List(vec![0.1, 0.01]),
+            lambdas: Some(Values::Range { min: 1e-3, max: 1e-1, count: 3 }),
+            l1_ratios: None,
+            batch_sizes: Some(Values::List(vec![1.0, 1.2, 4.0])),
+        };
+        let grid = space.grid();
+        // batch sizes 1 and 1.2 both round to 1
+        assert_eq!(grid.len(), 2 * 3 * 2);
+        assert!(grid.iter().all(|it| it.l1_ratio.is_none() && it.lambda.is_some()));
+        assert_eq!(grid[0], Candidate { ratio: 0.1, lambda: Some(1e-3), l1_ratio: None, batch_size: Some(1) });
+    }
+
+    #[test]
+    fn random_trials_are_seeded_and_in_range() {
+        let space = Space {
+            ratios: Values::Range { min: 1e-4, max: 1e-1, count: 1 },
+            lambdas: None,
+            l1_ratios: Some(Values::Range { min: 0.0, max: 1.0, count: 1 }),
+            batch_sizes: None,
+        };
+        let trials = space.random(20, &mut Random::new(5));
+        assert_eq!(trials.len(), 20);
+        assert!(trials.iter().all(|it| (1e-4..=1e-1).contains(&it.ratio) && (0.0..=1.0).contains(&it.l1_ratio.unwrap())));
+        assert_eq!(trials, space.random(20, &mut Random::new(5)));
+    }
+
+    #[test]
+    fn parallel_map_keeps_the_order() {
+        let items: Vec<usize> = (0..100).collect();
+        for threads in [1, 3, 200] {
+            assert_eq!(parallel_map(&items, threads, |it| it * it), items.iter().map(|it| it * it).collect::<Vec<_>>());
+        }
+        assert!(parallel_map(&[] as &[usize], 4, |it| *it).is_empty());
+    }
+}
@@ -0,0 +1,639 @@
+use crate::args::{ArgParser, DefaultArgParser, F64Parser, UsizeParser};
+use crate::dataset::{Dataset, DatasetEntry};
+use crate::estimate_price::estimate_price;
+use crate::linalg::{Matrix, Qr};
+use crate::convergence::{StopCriteria, StopReason};
+use crate::codec::{put_u8, put_u64, put_f64, ByteReader};
+use crate::random::Random;
+use crate::optimizer::OptimizerKind;
+use crate::schedule::{Schedule, backtrack};
+use crate::penalty::{Penalty, soft_threshold};
+use crate::history::History;
+use crate::checkpoint::Checkpoint;
+use crate::optimizer::Optimizer;
+use crate::scaler::{Scaler, ScalerKind};
+use crate::theta::Model;
+use std::time::{Duration, Instant};
+
+pub struct LearnRatioArg;
+
+impl F64Parser<'_> for LearnRatioArg {
+    const NAMES: &'static [&'static str] = &["-r", "--ratio"];
+    const DESCRIPTION: &'static str = "The Learning Ratio";
+}
+
+impl DefaultArgParser<'_, f64> for LearnRatioArg {
+    const DEFAULT: f64 = 0.00001;
+}
+
+pub struct BatchSizeArg;
+
+impl UsizeParser<'_> for BatchSizeArg {
+    const NAMES: &'static [&'static str] = &["--batch-size"];
+    const DESCRIPTION: &'static str = "Rows per gradient descent update, 1 for stochastic gradient descent, the whole dataset when not set";
+}
+
+pub struct SolverArg;
+
+impl<'a> ArgParser<'a, Solver> for SolverArg {
+    const NAMES: &'static [&'static str] = &["--solver"];
+    const VALUES: &'static [&'static str] = &["gd", "ols", "cd"];
+    const DESCRIPTION: &'static str = "Gradient descent, the exact least squares solution, or coordinate descent for lasso and elastic net";
+
+    fn parse_arg_value(value: Option<&'a str>) -> Result<Solver, String> {
+        match value {
+            Some("gd") => Ok(Solver::GradientDescent),
+            Some("ols") => Ok(Solver::Ols),
+            Some("cd") => Ok(Solver::CoordinateDescent),
+            Some(value) => Err(format!("Invalid value \"{}\", must be one of {}", value, Self::VALUES.join(", "))),
+            None => Err("Arg value is not optional, --help for more info".into()),
+        }
+    }
+}
+
+impl DefaultArgParser<'_, Solver> for SolverArg {
+    const DEFAULT: Solver = Solver::GradientDescent;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Solver {
+    GradientDescent,
+    /// Ordinary least squares through a QR decomposition of the design matrix, ridge penalty included
+    Ols,
+    /// Cyclic coordinate descent, exact minimization along one coefficient at a time
+    CoordinateDescent,
+}
+
+impl Solver {
+    fn id(self) -> u8 {
+        match self {
+            Solver::GradientDescent => 0,
+            Solver::Ols => 1,
+            Solver::CoordinateDescent => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, String> {
+        match id {
+            0 => Ok(Solver::GradientDescent),
+            1 => Ok(Solver::Ols),
+            2 => Ok(Solver::CoordinateDescent),
+            _ => Err(format!("unknown solver {}", id)),
+        }
+    }
+}
+
+/// How a model was trained, saved with it
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingSummary {
+    pub solver: Solver,
+    pub iterations: usize,
+    pub seconds: f64,
+    /// The rule that ended gradient descent, none for closed-form solvers
+    pub stop: Option<StopReason>,
+}
+
+impl TrainingSummary {
+    /// Section payload: solver as u8, iterations as u64, seconds as f64, then the stop reason as u8, 0xff for none
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        put_u8(out, self.solver.id());
+        put_u64(out, self.iterations as u64);
+        put_f64(out, self.seconds);
+        put_u8(out, self.stop.map_or(0xff, StopReason::id));
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        Ok(Self {
+            solver: Solver::from_id(reader.u8("solver")?)?,
+            iterations: reader.u64("iteration count")? as usize,
+            seconds: reader.f64("training time")?,
+            stop: match reader.u8("stop reason")? {
+                0xff => None,
+                id => Some(StopReason::from_id(id)?),
+            },
+        })
+    }
+}
+
+/// Mini-batch settings, saved with the model so the run can be reproduced
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Batching {
+    /// 1 for stochastic gradient descent
+    pub batch_size: usize,
+    /// Seed of the per-epoch shuffling
+    pub seed: u64,
+}
+
+impl Batching {
+    /// Section payload: batch size as u64, then the seed as u64
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        put_u64(out, self.batch_size as u64);
+        put_u64(out, self.seed);
+    }
+
+    pub(crate) fn decode(reader: &mut ByteReader) -> Result<Self, String> {
+        Ok(Self {
+            batch_size: reader.u64("batch size")? as usize,
+            seed: reader.u64("seed")?,
+        })
+    }
+}
+
+/// Everything gradient descent needs besides the data
+#[derive(Clone, Debug, PartialEq)]
+pub struct GdParams {
+    pub ratio: f64,
+    pub criteria: StopCriteria,
+    pub optimizer: OptimizerKind,
+    pub schedule: Schedule,
+    pub penalty: Penalty,
+    /// Full batch gradient descent when not set
+    pub batching: Option<Batching>,
+    /// Iterations, or epochs, between two calls of the checkpoint callback, none when not set
+    pub checkpoint_every: Option<usize>,
+    /// Prints progress and the final summary
+    pub verbose: bool,
+}
+
+/// Fits a model on raw rows: scales them, runs `solver` from scratch and keeps the scaler with the coefficients.
+/// Quiet, without history nor checkpoints, for callers training many models.
+pub fn fit(raw: &Dataset, scaler: ScalerKind, solver: Solver, params: &GdParams) -> Result<Model, String> {
+    let scaler = Scaler::fit(scaler, raw);
+    let scaled = scaler.transform(raw);
+    let params = GdParams { checkpoint_every: None, verbose: false, ..params.clone() };
+    let (theta, training) = match solver {
+        Solver::Ols => {
+            let start = Instant::now();
+            let theta = ols(&scaled, params.penalty)?;
+            (theta, TrainingSummary { solver, iterations: 0, seconds: start.elapsed().as_secs_f64(), stop: None })
+        }
+        Solver::CoordinateDescent => coordinate_descent(&scaled, params.penalty, &params.criteria, false, &mut History::default()),
+        Solver::GradientDescent => gradient_descent(&scaled, &params, Start::Zero, &mut History::default(), &mut |_, _| {}),
+    };
+    if training.stop == Some(StopReason::Diverged) {
+        return Err("gradient descent diverged, try a smaller ratio".into());
+    }
+    Ok(Model {
+        theta,
+        features: scaled.features,
+        scaler: Some(scaler),
+        partial: training.stop == Some(StopReason::Interrupted),
+        training: Some(training),
+        batching: params.batching.filter(|_| solver == Solver::GradientDescent),
+        penalty: params.penalty,
+        ..Model::default()
+    })
+}
+
+/// Where gradient descent starts from
+#[derive(Clone, Debug, PartialEq)]
+pub enum Start {
+    /// theta = 0
+    Zero,
+    /// Given coefficients, with a fresh optimizer and iteration count
+    Theta(Vec<f64>),
+    /// Given coefficients and the rest of the state a run was in, checked with [Checkpoint::check]
+    Resume(Vec<f64>, Checkpoint),
+}
+
+/// The state a run begins with: theta, optimizer, iteration count, last cost and a start time offset by the time already spent
+fn begin(len: usize, params: &GdParams, start: Start) -> (Vec<f64>, Box<dyn Optimizer>, usize, f64, Instant, Option<Checkpoint>) {
+    let mut optimizer = params.optimizer.build(len);
+    match start {
+        Start::Zero => (vec![0.0; len], optimizer, 0, f64::INFINITY, Instant::now(), None),
+        Start::Theta(theta) => (theta, optimizer, 0, f64::INFINITY, Instant::now(), None),
+        Start::Resume(theta, checkpoint) => {
+            optimizer.restore(&checkpoint.optimizer_state);
+            let now = Instant::now();
+            let start = now.checked_sub(Duration::from_secs_f64(checkpoint.seconds.max(0.0))).unwrap_or(now);
+            (theta, optimizer, checkpoint.iterations, checkpoint.last_cost, start, Some(checkpoint))
+        }
+    }
+}
+
+/// Mean squared error of theta over the dataset
+pub fn cost(dataset: &Dataset, theta: &[f64]) -> f64 {
+    dataset.entries.iter().map(|DatasetEntry { features, price }| {
+        let error = estimate_price(features, theta) - price;
+        error * error
+    }).sum::<f64>() / dataset.entries.len() as f64
+}
+
+/// The exact minimum of the squared error, plus the ridge penalty if any.
+/// L1 penalties have no closed form, they need coordinate or gradient descent.
+pub fn ols(dataset: &Dataset, penalty: Penalty) -> Result<Vec<f64>, String> {
+    if penalty.l1() != 0.0 {
+        return Err(format!("{} penalty has no closed form, use coordinate or gradient descent", penalty));
+    }
+    let cols = dataset.features.len() + 1;
+    let rows = dataset.entries.len();
+    // the ridge penalty is the squared error of extra rows asking each coefficient but the intercept to be 0
+    let ridge = if penalty.l2() != 0.0 { cols - 1 } else { 0 };
+    let mut design = Matrix::zeros(rows + ridge, cols);
+    for (row, entry) in dataset.entries.iter().enumerate() {
+        design.set(row, 0, 1.0);
+        entry.features.iter().enumerate().for_each(|(col, it)| design.set(row, col + 1, *it));
+    }
+    let weight = (rows as f64 * penalty.l2()).sqrt();
+    (0..ridge).for_each(|idx| design.set(rows + idx, idx + 1, weight));
+    let mut prices: Vec<_> = dataset.entries.iter().map(|it| it.price).collect();
+    prices.resize(rows + ridge, 0.0);
+    Ok(Qr::new(design)?.solve(&prices))
+}
+
+/// Minimizes the penalized cost one coefficient at a time, from theta = 0, until one of the stop criteria holds.
+/// Each sweep over every coefficient counts as an iteration, the gradient tolerance does not apply.
+pub fn coordinate_descent(dataset: &Dataset, penalty: Penalty, criteria: &StopCriteria, verbose: bool, history: &mut History) -> (Vec<f64>, TrainingSummary) {
+    let count = dataset.entries.len() as f64;
+    let mut theta = vec![0.0; dataset.features.len() + 1];
+    let mut last = theta.clone();
+    // prices minus predictions, kept up to date with each coefficient change
+    let mut residuals: Vec<f64> = dataset.entries.iter().map(|it| it.price).collect();
+    let norms: Vec<f64> = (0..dataset.features.len()).map(|col| {
+        dataset.entries.iter().map(|it| it.features[col] * it.features[col]).sum::<f64>() / count
+    }).collect();
+    let (l1, l2) = (penalty.l1(), penalty.l2());
+
+    let mut iter: usize = 0;
+    let mut last_cost = f64::INFINITY;
+    let start = Instant::now();
+    let stop = loop {
+        last.copy_from_slice(&theta);
+        // the intercept is not penalized, its optimum is the mean residual
+        let shift = residuals.iter().sum::<f64>() / count;
+        theta[0] += shift;
+        residuals.iter_mut().for_each(|it| *it -= shift);
+        for (col, norm) in norms.iter().enumerate() {
+            let old = theta[col + 1];
+            let rho = dataset.entries.iter().zip(&residuals).map(|(entry, residual)| entry.features[col] * residual).sum::<f64>() / count + norm * old;
+            let new = if *norm > 0.0 { soft_threshold(rho, l1) / (norm + l2) } else { 0.0 };
+            if new != old {
+                dataset.entries.iter().zip(&mut residuals).for_each(|(entry, residual)| *residual -= entry.features[col] * (new - old));
+                theta[col + 1] = new;
+            }
+        }
+        iter += 1;
+        let cost = residuals.iter().map(|it| it * it).sum::<f64>() / count;
+        history.record(iter, cost, f64::NAN);
+        if let Some(stop) = criteria.check(iter, &last, &theta, (last_cost - cost).abs(), f64::INFINITY).or_else(|| criteria.check_time(&start)) {
+            break stop;
+        }
+        last_cost = cost;
+    };
+    let seconds = start.elapsed().as_secs_f64();
+    if verbose {
+        println!("Done {} coordinate descent sweeps in {:.3}s, stopped by {}", iter, seconds, stop);
+    }
+    (theta, TrainingSummary { solver: Solver::CoordinateDescent, iterations: iter, seconds, stop: Some(stop) })
+}
+
+/// Adds the squared error gradient of each entry to `gradient` and returns the sum of squared errors
+#[inline]
+fn accumulate<'a>(entries: impl Iterator<Item=&'a DatasetEntry>, theta: &[f64], gradient: &mut [f64]) -> f64 {
+    gradient.iter_mut().for_each(|it| *it = 0.0);
+    entries.map(|DatasetEntry { features, price }| {
+        // use the temporary t0 so we don't compute things twice
+        let t0 = estimate_price(features, theta) - price;
+        gradient[0] += t0;
+        gradient[1..].iter_mut().zip(features).for_each(|(gradient, feature)| *gradient += t0 * feature);
+        t0 * t0
+    }).sum()
+}
+
+/// Gradient descent from `start` until one of the stop criteria holds, the cost going to `history` along the way
+/// and the state to `on_checkpoint` every `params.checkpoint_every` iterations
+pub fn gradient_descent(dataset: &Dataset, params: &GdParams, start: Start, history: &mut History, on_checkpoint: &mut dyn FnMut(&[f64], Checkpoint)) -> (Vec<f64>, TrainingSummary) {
+    match params.batching {
+        None => full_batch(dataset, params, start, history, on_checkpoint),
+        Some(batching) => mini_batch(dataset, params, batching, start, history, on_checkpoint),
+    }
+}
+
+/// What every gradient descent loop does after a step: checks the stop criteria, hands out checkpoints and reports progress
+struct Progress<'a> {
+    params: &'a GdParams,
+    start: Instant,
+    /// Cost of the last step, the cost tolerance compares with it
+    last_cost: f64,
+    last_report: Instant,
+    /// What a step is, "iterations" or "epochs"
+    unit: &'static str,
+    /// The clock is read when the step count has none of these bits set, reading it every step would slow tight loops
+    clock_mask: usize,
+    on_checkpoint: &'a mut dyn FnMut(&[f64], Checkpoint),
+}
+
+impl<'a> Progress<'a> {
+    fn new(params: &'a GdParams, start: Instant, last_cost: f64, unit: &'static str, clock_mask: usize, on_checkpoint: &'a mut dyn FnMut(&[f64], Checkpoint)) -> Self {
+        Self { params, start, last_cost, last_report: Instant::now(), unit, clock_mask, on_checkpoint }
+    }
+
+    /// Why to stop after step `iter` went from `last` to `theta`, if it should. `cost` and `gradient_norm` are those the step measured,
+    /// `state` gives the optimizer state and the shuffle of a checkpoint, only called when one is due.
+    #[inline(always)]
+    fn check(&mut self, iter: usize, last: &[f64], theta: &[f64], cost: f64, gradient_norm: f64, state: impl FnOnce() -> (Vec<f64>, Option<(u64, Vec<usize>)>)) -> Option<StopReason> {
+        let criteria = &self.params.criteria;
+        let timed = iter & self.clock_mask == 0;
+        let stop = criteria.check(iter, last, theta, (self.last_cost - cost).abs(), gradient_norm)
+            .or_else(|| if timed { criteria.check_time(&self.start) } else { None });
+        self.last_cost = cost;
+        if let Some(every) = self.params.checkpoint_every {
+            // the state a run stopped in is kept too, so it can be extended, unless it diverged
+            if iter.is_multiple_of(every) || (stop.is_some() && stop != Some(StopReason::Diverged)) {
+                let (optimizer_state, shuffle) = state();
+                (self.on_checkpoint)(theta, Checkpoint {
+                    iterations: iter,
+                    seconds: self.start.elapsed().as_secs_f64(),
+                    last_cost: cost,
+                    ratio: self.params.ratio,
+                    optimizer: self.params.optimizer,
+                    schedule: self.params.schedule,
+                    penalty: self.params.penalty,
+                    optimizer_state,
+                    shuffle,
+                });
+            }
+        }
+        if self.params.verbose && timed && stop.is_none() {
+            self.report(iter, cost, theta);
+        }
+        stop
+    }
+
+    // Cold function because we want the loops to be tight, thus not have all this garbage inlined
+    #[cold]
+    fn report(&mut self, iter: usize, cost: f64, theta: &[f64]) {
+        // at most one report per second, small datasets go through thousands of steps per second
+        if self.last_report.elapsed().as_secs_f64() >= 1.0 {
+            self.last_report = Instant::now();
+            let seconds = self.start.elapsed().as_secs_f64();
+            // plain full batch only computes the cost when something else reads it
+            if cost.is_nan() {
+                println!("{} {} after {:.3}s, theta is {:?}", iter, self.unit, seconds, theta);
+            } else {
+                println!("{} {} after {:.3}s, cost is {:e}, theta is {:?}", iter, self.unit, seconds, cost, theta);
+            }
+        }
+    }
+
+    /// Prints how the run ended, `detail` following the step count
+    fn finish(&self, iter: usize, stop: StopReason, detail: &str) -> TrainingSummary {
+        let seconds = self.start.elapsed().as_secs_f64();
+        if self.params.verbose {
+            println!("Done {} {}{} in {:.3}s, stopped by {}", iter, self.unit, detail, seconds, stop);
+        }
+        TrainingSummary { solver: Solver::GradientDescent, iterations: iter, seconds, stop: Some(stop) }
+    }
+}
+
+fn full_batch(dataset: &Dataset, params: &GdParams, start: Start, history: &mut History, on_checkpoint: &mut dyn FnMut(&[f64], Checkpoint)) -> (Vec<f64>, TrainingSummary) {
+    // the default training is plain gradient descent on a few features, it gets a loop free of dynamic dispatch
+    if params.optimizer == OptimizerKind::Vanilla && params.schedule == Schedule::Constant && params.penalty == Penalty::None {
+        match dataset.features.len() {
+            1 => return plain_full_batch::<2>(dataset, params, start, history, on_checkpoint),
+            2 => return plain_full_batch::<3>(dataset, params, start, history, on_checkpoint),
+            3 => return plain_full_batch::<4>(dataset, params, start, history, on_checkpoint),
+            4 => return plain_full_batch::<5>(dataset, params, start, history, on_checkpoint),
+            _ => {}
+        }
+    }
+    let (mut theta, mut optimizer, mut iter, last_cost, start, _) = begin(dataset.features.len() + 1, params, start);
+    let mut progress = Progress::new(params, start, last_cost, "iterations", 0xff, on_checkpoint);
+    let mut last = theta.clone();
+    let mut gradient = theta.clone();
+
+    let count = dataset.entries.len() as f64;
+    let stop = loop {
+        let cost = accumulate(dataset.entries.iter(), &theta, &mut gradient) / count;
+        gradient.iter_mut().for_each(|it| *it /= count);
+        params.penalty.add_gradient(&theta, &mut gradient);
+        let ratio = match params.schedule {
+            Schedule::Backtracking { shrink, armijo } => backtrack(dataset, &params.penalty, &theta, &gradient, cost, params.ratio, (shrink, armijo)),
+            schedule => schedule.ratio(params.ratio, iter),
+        };
+        last.copy_from_slice(&theta);
+        optimizer.step(&mut theta, &gradient, ratio);
+        params.penalty.prox(&mut theta, ratio);
+        let gradient_norm = gradient.iter().map(|it| it * it).sum::<f64>().sqrt();
+        // cost and gradient are those of theta before the step
+        history.record(iter, cost, gradient_norm);
+        iter += 1;
+        if let Some(stop) = progress.check(iter, &last, &theta, cost, gradient_norm, || (optimizer.state(), None)) {
+            break stop;
+        }
+    };
+    (theta, progress.finish(iter, stop, ""))
+}
+
+/// [full_batch] without optimizer, schedule nor penalty, on `D - 1` features. Theta, the gradient and the rows are arrays,
+/// the steps a division and an indirect call shorter, and the cost and gradient norm are only computed when something reads them.
+fn plain_full_batch<const D: usize>(dataset: &Dataset, params: &GdParams, start: Start, history: &mut History, on_checkpoint: &mut dyn FnMut(&[f64], Checkpoint)) -> (Vec<f64>, TrainingSummary) {
+    let criteria = &params.criteria;
+    let (initial, optimizer, mut iter, last_cost, start, _) = begin(D, params, start);
+    let mut progress = Progress::new(params, start, last_cost, "iterations", 0xff, on_checkpoint);
+    let mut theta = [0.0; D];
+    theta.copy_from_slice(&initial);
+    // the price, then the features
+    let rows: Vec<[f64; D]> = dataset.entries.iter().map(|it| {
+        let mut row = [it.price; D];
+        row[1..].copy_from_slice(&it.features);
+        row
+    }).collect();
+    let wants_cost = history.stride != 0 || criteria.cost_tolerance.is_some() || params.checkpoint_every.is_some();
+    let wants_norm = history.stride != 0 || criteria.gradient_tolerance.is_some();
+
+    let count = rows.len() as f64;
+    // a division per coefficient and per iteration would lengthen the chain from one theta to the next, the summed gradient is scaled once
+    let step = params.ratio / count;
+    let stop = loop {
+        let mut gradient = [0.0; D];
+        let mut cost = if wants_cost { 0.0 } else { f64::NAN };
+        for row in &rows {
+            let t0 = estimate_price(&row[1..], &theta) - row[0];
+            gradient[0] += t0;
+            gradient[1..].iter_mut().zip(&row[1..]).for_each(|(gradient, feature)| *gradient += t0 * feature);
+            if wants_cost {
+                cost += t0 * t0;
+            }
+        }
+        let cost = cost / count;
+        let last = theta;
+        theta.iter_mut().zip(&gradient).for_each(|(theta, gradient)| *theta -= gradient * step);
+        let gradient_norm = if wants_norm { gradient.iter().map(|it| it * it).sum::<f64>().sqrt() / count } else { f64::NAN };
+        history.record(iter, cost, gradient_norm);
+        iter += 1;
+        if let Some(stop) = progress.check(iter, &last, &theta, cost, gradient_norm, || (optimizer.state(), None)) {
+            break stop;
+        }
+    };
+    (theta.to_vec(), progress.finish(iter, stop, ""))
+}
+
+/// Updates theta once per batch of shuffled rows. Stop criteria are checked once per epoch,
+/// on the full dataset cost and gradient at the end of the epoch, and iterations count epochs.
+/// The ratio follows the schedule epoch by epoch, backtracking is not available and keeps it constant.
+fn mini_batch(dataset: &Dataset, params: &GdParams, batching: Batching, start: Start, history: &mut History, on_checkpoint: &mut dyn FnMut(&[f64], Checkpoint)) -> (Vec<f64>, TrainingSummary) {
+    let (mut theta, mut optimizer, mut epoch, last_cost, start, checkpoint) = begin(dataset.features.len() + 1, params, start);
+    let mut progress = Progress::new(params, start, last_cost, "epochs", 0, on_checkpoint);
+    let mut last = theta.clone();
+    let mut gradient = theta.clone();
+
+    let count = dataset.entries.len() as f64;
+    let (mut random, mut order) = match checkpoint.and_then(|it| it.shuffle) {
+        Some((state, order)) => (Random::new(state), order),
+        None => (Random::new(batching.seed), (0..dataset.entries.len()).collect()),
+    };
+
+    let stop = loop {
+        random.shuffle(&mut order);
+        let ratio = params.schedule.ratio(params.ratio, epoch);
+        last.copy_from_slice(&theta);
+        for batch in order.chunks(batching.batch_size.max(1)) {
+            accumulate(batch.iter().map(|idx| &dataset.entries[*idx]), &theta, &mut gradient);
+            gradient.iter_mut().for_each(|it| *it /= batch.len() as f64);
+            params.penalty.add_gradient(&theta, &mut gradient);
+            optimizer.step(&mut theta, &gradient, ratio);
+            params.penalty.prox(&mut theta, ratio);
+        }
+        epoch += 1;
+        let cost = accumulate(dataset.entries.iter(), &theta, &mut gradient) / count;
+        gradient.iter_mut().for_each(|it| *it /= count);
+        params.penalty.add_gradient(&theta, &mut gradient);
+        let gradient_norm = gradient.iter().map(|it| it * it).sum::<f64>().sqrt();
+        history.record(epoch, cost, gradient_norm);
+        if let Some(stop) = progress.check(epoch, &last, &theta, cost, gradient_norm, || (optimizer.state(), Some((random.state(), order.clone())))) {
+            break stop;
+        }
+    };
+    (theta, progress.finish(epoch, stop, &format!(" of batch size {} with seed {}", batching.batch_size, batching.seed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// price = 2 + 3 x1 - 0.5 x2 exactly
+    fn linear() -> Dataset {
+        let rows = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (0.5, 0.25), (0.2, 0.9), (0.8, 0.6)];
+        Dataset {
+            features: vec!["x1".into(), "x2".into()],
+            entries: rows.iter().map(|&(x1, x2)| DatasetEntry { features: vec![x1, x2], price: 2.0 + 3.0 * x1 - 0.5 * x2 }).collect(),
+        }
+    }
+
+    fn params(ratio: f64, criteria: StopCriteria, batching: Option<Batching>) -> GdParams {
+        GdParams { ratio, criteria, optimizer: OptimizerKind::Vanilla, schedule: Schedule::Constant, penalty: Penalty::None, batching, checkpoint_every: None, verbose: false }
+    }
+
+    fn descend(dataset: &Dataset, params: &GdParams) -> (Vec<f64>, TrainingSummary) {
+        gradient_descent(dataset, params, Start::Zero, &mut History::default(), &mut |_, _| {})
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64], tolerance: f64) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() <= tolerance, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn ols_recovers_exact_coefficients() {
+        let theta = ols(&linear(), Penalty::None).unwrap();
+        assert_close(&theta, &[2.0, 3.0, -0.5], 1e-12);
+        assert!(cost(&linear(), &theta) < 1e-24);
+    }
+
+    #[test]
+    fn ols_rejects_collinear_features() {
+        let mut dataset = linear();
+        dataset.entries.iter_mut().for_each(|it| it.features[1] = 2.0 * it.features[0]);
+        assert!(ols(&dataset, Penalty::None).is_err());
+    }
+
+    #[test]
+    fn gradient_descent_reaches_ols() {
+        let dataset = linear();
+        let params = params(0.5, StopCriteria::default(), None);
+        let (theta, summary) = descend(&dataset, &params);
+        assert_eq!(summary.stop, Some(StopReason::Tolerance));
+        assert_close(&theta, &ols(&dataset, Penalty::None).unwrap(), 1e-9);
+    }
+
+    #[test]
+    fn mini_batch_is_seeded_and_reaches_ols() {
+        let dataset = linear();
+        let criteria = StopCriteria { tolerance: 1e-13, max_iterations: Some(100_000), ..StopCriteria::default() };
+        let params = params(0.2, criteria, Some(Batching { batch_size: 2, seed: 7 }));
+        let (theta, summary) = descend(&dataset, &params);
+        assert_eq!(summary.stop, Some(StopReason::Tolerance));
+        assert_close(&theta, &ols(&dataset, Penalty::None).unwrap(), 1e-9);
+        assert_eq!(descend(&dataset, &params).0, theta);
+    }
+
+    /// price = 2 + 3 x1 - 0.5 x2 plus noise, so penalties have something to trade off
+    fn noisy() -> Dataset {
+        let mut dataset = linear();
+        let noise = [0.1, -0.2, 0.05, 0.15, -0.1, 0.0];
+        dataset.entries.iter_mut().zip(&noise).for_each(|(entry, noise)| entry.price += noise);
+        dataset
+    }
+
+    fn tight() -> StopCriteria {
+        StopCriteria { tolerance: 1e-14, max_iterations: Some(1_000_000), ..StopCriteria::default() }
+    }
+
+    #[test]
+    fn coordinate_descent_reaches_ols() {
+        let dataset = noisy();
+        let (theta, summary) = coordinate_descent(&dataset, Penalty::None, &tight(), false, &mut History::default());
+        assert_eq!(summary.stop, Some(StopReason::Tolerance));
+        assert_close(&theta, &ols(&dataset, Penalty::None).unwrap(), 1e-9);
+    }
+
+    #[test]
+    fn ridge_closed_form_matches_descents() {
+        let dataset = noisy();
+        let penalty = Penalty::Ridge { lambda: 0.1 };
+        let exact = ols(&dataset, penalty).unwrap();
+        assert!(exact[1..].iter().map(|it| it * it).sum::<f64>() < ols(&dataset, Penalty::None).unwrap()[1..].iter().map(|it| it * it).sum::<f64>());
+        let (theta, _) = descend(&dataset, &GdParams { penalty, ..params(0.5, tight(), None) });
+        assert_close(&theta, &exact, 1e-9);
+        let (theta, _) = coordinate_descent(&dataset, penalty, &tight(), false, &mut History::default());
+        assert_close(&theta, &exact, 1e-9);
+    }
+
+    #[test]
+    fn lasso_descents_agree() {
+        let dataset = noisy();
+        let penalty = Penalty::ElasticNet { lambda: 0.05, l1_ratio: 0.7 };
+        assert!(ols(&dataset, penalty).is_err());
+        let (cd, _) = coordinate_descent(&dataset, penalty, &tight(), false, &mut History::default());
+        let (gd, _) = descend(&dataset, &GdParams { penalty, ..params(0.5, tight(), None) });
+        assert_close(&gd, &cd, 1e-9);
+        // a large enough L1 weight zeroes every coefficient but the intercept
+        let (theta, _) = coordinate_descent(&dataset, Penalty::Lasso { lambda: 10.0 }, &tight(), false, &mut History::default());
+        assert_eq!(&theta[1..], &[0.0, 0.0]);
+        let mean = dataset.entries.iter().map(|it| it.price).sum::<f64>() / dataset.entries.len() as f64;
+        assert!((theta[0] - mean).abs() < 1e-12);
+    }
+
+    #[test]
+    fn resume_matches_an_uninterrupted_run() {
+        let dataset = noisy();
+        for batching in [None, Some(Batching { batch_size: 2, seed: 3 })] {
+            let params = GdParams {
+                optimizer: OptimizerKind::Adam { beta1: 0.9, beta2: 0.999, epsilon: 1e-8 },
+                checkpoint_every: Some(100),
+                ..params(0.01, StopCriteria { max_iterations: Some(200), ..StopCriteria::default() }, batching)
+            };
+            let mut checkpoints = vec![];
+            let (theta, _) = gradient_descent(&dataset, &params, Start::Zero, &mut History::default(), &mut |theta, checkpoint| checkpoints.push((theta.to_vec(), checkpoint)));
+            assert_eq!(checkpoints.iter().map(|(_, it)| it.iterations).collect::<Vec<_>>(), [100, 200]);
+            let (halfway, checkpoint) = checkpoints.swap_remove(0);
+            checkpoint.check(&dataset, &params).unwrap();
+            let (resumed, summary) = gradient_descent(&dataset, &params, Start::Resume(halfway, checkpoint), &mut History::default(), &mut |_, _| {});
+            assert_eq!(summary.iterations, 200);
+            assert_eq!(resumed, theta, "{:?}", batching);
+        }
+    }
+}
@@ -1,9 +1,17 @@
-use std::fs::{OpenOptions};
+use std::fs::{self, OpenOptions};
 use std::path::Path;
 use std::io::{Read, Write};
 use std::convert::TryInto;
 use crate::args::{FileParser};
-use std::error::Error;
+use crate::codec::{put_u16, put_u32, put_f64, put_str, crc32, ByteReader};
+use crate::scaler::Scaler;
+use crate::solver::{TrainingSummary, Batching};
+use crate::penalty::Penalty;
+use crate::checkpoint::Checkpoint;
+use crate::metrics::{Split, Evaluation};
+use crate::inference::IntervalStats;
+use crate::range::TrainingRange;
+use crate::estimate_price::estimate_price;
 
 pub struct ThetaFileArg;
 
@@ -12,63 +20,480 @@ impl FileParser<'_> for ThetaFileArg {
     const DESCRIPTION: &'static str = "The Theta variable file path";
 }
 
-fn read_theta(path: Option<&Path>) -> Result<(f64, f64), ()> {
-    fn read(path: &Path) -> Result<(f64, f64), ()> {
-        match OpenOptions::new().read(true).open(path) {
-            Ok(mut file) => {
-                let mut bytes: Vec<u8> = vec![];
-                let _ = file.read_to_end(&mut bytes).map_err(|err| println!("Error: theta file {} could not be read: {}", path.display(), err))?;
-                drop(file);
-                if bytes.len() != 16 {
-                    Err(println!("Error: theta file {} must be 16 bytes long: two big endian f64 values", path.display()))
-                } else {
-                    Ok((f64::from_be_bytes(bytes[0..8].try_into().unwrap()), f64::from_be_bytes(bytes[8..16].try_into().unwrap())))
-                }
+/// Where the model is read from and saved to without --file
+pub const DEFAULT_PATH: &str = "./theta";
+
+/// Every model file starts with these bytes
+const MAGIC: &[u8; 4] = b"FTLR";
+/// Latest format version this build can write, files with a higher version are refused
+pub const FORMAT_VERSION: u16 = 1;
+/// The headerless format of earlier versions: two big endian f64 values, theta0 then theta1
+const LEGACY_LEN: usize = 16;
+
+/// A trained model: its coefficients, the features they apply to and,
+/// when it was trained on scaled data, the scaler to apply around them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Model {
+    /// theta0 then one coefficient per feature, in the scaled space when `scaler` is set, raw otherwise
+    pub theta: Vec<f64>,
+    /// Feature names, in the order the coefficients expect them
+    pub features: Vec<String>,
+    pub scaler: Option<Scaler>,
+    pub training: Option<TrainingSummary>,
+    pub batching: Option<Batching>,
+    /// The regularization the coefficients were fitted with
+    pub penalty: Penalty,
+    /// Set on checkpoints, what gradient descent needs to resume
+    pub checkpoint: Option<Checkpoint>,
+    /// Training was interrupted before any stop criterion held
+    pub partial: bool,
+    /// The holdout split of the dataset it was trained on
+    pub split: Option<Split>,
+    pub evaluation: Option<Evaluation>,
+    /// Residual spread of the training rows, for prediction intervals
+    pub interval_stats: Option<IntervalStats>,
+    /// What the features looked like in the training rows, to tell extrapolations apart
+    pub range: Option<TrainingRange>,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self { theta: vec![0.0, 0.0], features: vec![LEGACY_FEATURE.into()], scaler: None, training: None, batching: None, penalty: Penalty::None, checkpoint: None, partial: false, split: None, evaluation: None, interval_stats: None, range: None }
+    }
+}
+
+impl Model {
+    pub fn predict(&self, features: &[f64]) -> f64 {
+        match &self.scaler {
+            Some(scaler) => scaler.price.inverse_transform(estimate_price(&scaler.transform_features(features), &self.theta)),
+            None => estimate_price(features, &self.theta),
+        }
+    }
+
+    /// Coefficients applying directly to raw feature values and prices
+    pub fn raw_theta(&self) -> Vec<f64> {
+        match &self.scaler {
+            Some(scaler) => scaler.inverse_transform_theta(&self.theta),
+            None => self.theta.clone(),
+        }
+    }
+
+    /// Checks that a dataset provides the features this model was trained on, in the same order
+    pub fn check_features(&self, features: &[String]) -> Result<(), String> {
+        if features.len() == self.features.len() && features.iter().zip(&self.features).all(|(a, b)| a.eq_ignore_ascii_case(b)) {
+            Ok(())
+        } else {
+            Err(format!("model expects features [{}], got [{}]", self.features.join(", "), features.join(", ")))
+        }
+    }
+}
+
+/// The single feature of legacy files and of files written before feature names were saved
+const LEGACY_FEATURE: &str = "km";
+
+const SCALER_TAG: &[u8; 4] = b"SCAL";
+const FEATURES_TAG: &[u8; 4] = b"FEAT";
+const TRAINING_TAG: &[u8; 4] = b"TRAN";
+const BATCHING_TAG: &[u8; 4] = b"BTCH";
+const PENALTY_TAG: &[u8; 4] = b"PNLT";
+const CHECKPOINT_TAG: &[u8; 4] = b"CKPT";
+const PARTIAL_TAG: &[u8; 4] = b"PART";
+const SPLIT_TAG: &[u8; 4] = b"SPLT";
+const EVALUATION_TAG: &[u8; 4] = b"METR";
+const INTERVAL_TAG: &[u8; 4] = b"INTV";
+const RANGE_TAG: &[u8; 4] = b"RANG";
+
+/// The model file layout, all values are big endian:
+///
+/// | field         | type                      |
+/// |---------------|---------------------------|
+/// | magic         | `FTLR`                    |
+/// | version       | u16                       |
+/// | feature count | u32                       |
+/// | coefficients  | (feature count + 1) × f64, theta0 first, on raw values |
+/// | section count | u32                       |
+/// | sections      | 4 byte tag, u32 length, payload |
+/// | checksum      | u32, CRC-32 of everything before it |
+///
+/// Sections hold optional data, readers skip the tags they do not know, so no section may change what the header means:
+/// - `SCAL`: the [Scaler] the coefficients were fitted with, then the coefficients in the scaled space as (feature count + 1) × f64.
+///   The header keeps the raw ones, readers without scaling predict the same prices.
+/// - `FEAT`: the feature names, u32 count then each name as a length prefixed string
+/// - `TRAN`: the [TrainingSummary] of the run that produced the model
+/// - `BTCH`: the [Batching] of a mini-batch or stochastic run
+/// - `PNLT`: the [Penalty] of a regularized model
+/// - `CKPT`: the [Checkpoint] of an unfinished run
+/// - `PART`: empty, marks a model whose training was interrupted
+/// - `SPLT`: the holdout [Split] of the training dataset
+/// - `METR`: the [Evaluation] of the model on its training and test rows
+/// - `INTV`: the [IntervalStats] of the training rows
+/// - `RANG`: the [TrainingRange] of the features
+fn encode(model: &Model) -> Vec<u8> {
+    let theta = model.raw_theta();
+    let mut sections: Vec<(&[u8; 4], Vec<u8>)> = vec![];
+    let mut payload = vec![];
+    put_u32(&mut payload, model.features.len() as u32);
+    model.features.iter().for_each(|it| put_str(&mut payload, it));
+    sections.push((FEATURES_TAG, payload));
+    if let Some(scaler) = &model.scaler {
+        let mut payload = vec![];
+        scaler.encode(&mut payload);
+        model.theta.iter().for_each(|it| put_f64(&mut payload, *it));
+        sections.push((SCALER_TAG, payload));
+    }
+    if let Some(training) = &model.training {
+        let mut payload = vec![];
+        training.encode(&mut payload);
+        sections.push((TRAINING_TAG, payload));
+    }
+    if let Some(batching) = &model.batching {
+        let mut payload = vec![];
+        batching.encode(&mut payload);
+        sections.push((BATCHING_TAG, payload));
+    }
+    if model.penalty != Penalty::None {
+        let mut payload = vec![];
+        model.penalty.encode(&mut payload);
+        sections.push((PENALTY_TAG, payload));
+    }
+    if let Some(checkpoint) = &model.checkpoint {
+        let mut payload = vec![];
+        checkpoint.encode(&mut payload);
+        sections.push((CHECKPOINT_TAG, payload));
+    }
+    if model.partial {
+        sections.push((PARTIAL_TAG, vec![]));
+    }
+    if let Some(split) = &model.split {
+        let mut payload = vec![];
+        split.encode(&mut payload);
+        sections.push((SPLIT_TAG, payload));
+    }
+    if let Some(evaluation) = &model.evaluation {
+        let mut payload = vec![];
+        evaluation.encode(&mut payload);
+        sections.push((EVALUATION_TAG, payload));
+    }
+    if let Some(interval_stats) = &model.interval_stats {
+        let mut payload = vec![];
+        interval_stats.encode(&mut payload);
+        sections.push((INTERVAL_TAG, payload));
+    }
+    if let Some(range) = &model.range {
+        let mut payload = vec![];
+        range.encode(&mut payload);
+        sections.push((RANGE_TAG, payload));
+    }
+
+    let mut out = Vec::with_capacity(4 + 2 + 4 + theta.len() * 8 + 4 + 4);
+    out.extend_from_slice(MAGIC);
+    put_u16(&mut out, FORMAT_VERSION);
+    put_u32(&mut out, (theta.len() - 1) as u32);
+    theta.iter().for_each(|it| put_f64(&mut out, *it));
+    put_u32(&mut out, sections.len() as u32);
+    for (tag, payload) in sections {
+        out.extend_from_slice(tag);
+        put_u32(&mut out, payload.len() as u32);
+        out.extend_from_slice(&payload);
+    }
+    let checksum = crc32(&out);
+    put_u32(&mut out, checksum);
+    out
+}
+
+enum Format {
+    Current,
+    Legacy,
+}
+
+fn decode(bytes: &[u8]) -> Result<(Format, Model), String> {
+    if bytes.len() == LEGACY_LEN && !bytes.starts_with(MAGIC) {
+        let theta = vec![f64::from_be_bytes(bytes[0..8].try_into().unwrap()), f64::from_be_bytes(bytes[8..16].try_into().unwrap())];
+        return Ok((Format::Legacy, Model { theta, ..Model::default() }));
+    }
+    if !bytes.starts_with(MAGIC) {
+        return Err("not a model file, magic number is missing".into());
+    }
+    if bytes.len() < MAGIC.len() + 4 {
+        return Err(format!("truncated, only {} bytes long", bytes.len()));
+    }
+    let (content, checksum) = bytes.split_at(bytes.len() - 4);
+    let checksum = u32::from_be_bytes(checksum.try_into().unwrap());
+    if crc32(content) != checksum {
+        return Err("checksum mismatch, the file is truncated or corrupted".into());
+    }
+    let mut reader = ByteReader::new(&content[MAGIC.len()..]);
+    let version = reader.u16("format version")?;
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(format!("format version {} is not supported, this build reads up to version {}", version, FORMAT_VERSION));
+    }
+    let features = reader.u32("feature count")? as usize;
+    let theta = (0..=features).map(|idx| reader.f64(&format!("theta{}", idx))).collect::<Result<Vec<_>, _>>()?;
+    let mut model = Model { theta, features: vec![], ..Model::default() };
+    let sections = reader.u32("section count")?;
+    for idx in 0..sections {
+        let tag = reader.take(4, &format!("section {} tag", idx))?;
+        let len = reader.u32(&format!("section {} length", idx))? as usize;
+        let mut payload = ByteReader::new(reader.take(len, &format!("section {} payload", idx))?);
+        match tag {
+            t if t == SCALER_TAG => {
+                model.scaler = Some(Scaler::decode(&mut payload)?);
+                model.theta = (0..=features).map(|idx| payload.f64(&format!("scaled theta{}", idx))).collect::<Result<_, _>>()?;
             }
-            Err(err) => {
-                Err(println!("Error: theta file {} could not be opened with read permission: {}", path.display(), err))
+            t if t == TRAINING_TAG => model.training = Some(TrainingSummary::decode(&mut payload)?),
+            t if t == BATCHING_TAG => model.batching = Some(Batching::decode(&mut payload)?),
+            t if t == PENALTY_TAG => model.penalty = Penalty::decode(&mut payload)?,
+            t if t == CHECKPOINT_TAG => model.checkpoint = Some(Checkpoint::decode(&mut payload)?),
+            t if t == PARTIAL_TAG => model.partial = true,
+            t if t == SPLIT_TAG => model.split = Some(Split::decode(&mut payload)?),
+            t if t == EVALUATION_TAG => model.evaluation = Some(Evaluation::decode(&mut payload)?),
+            t if t == INTERVAL_TAG => model.interval_stats = Some(IntervalStats::decode(&mut payload)?),
+            t if t == RANGE_TAG => model.range = Some(TrainingRange::decode(&mut payload, features)?),
+            t if t == FEATURES_TAG => {
+                let count = payload.u32("feature name count")?;
+                model.features = (0..count).map(|idx| payload.str(&format!("feature {} name", idx))).collect::<Result<_, _>>()?;
             }
+            _ => {
+                println!("Warning: skipping unknown model section \"{}\"", String::from_utf8_lossy(tag));
+                continue;
+            }
+        }
+        if !payload.is_empty() {
+            return Err(format!("section \"{}\" is longer than expected", String::from_utf8_lossy(tag)));
+        }
+    }
+    if !reader.is_empty() {
+        return Err("unexpected trailing data after the last section".into());
+    }
+    if model.features.is_empty() && features == 1 {
+        model.features.push(LEGACY_FEATURE.into());
+    }
+    if model.features.len() != features {
+        return Err(format!("{} coefficients for {} feature names", features, model.features.len()));
+    }
+    if let Some(scaler) = &model.scaler {
+        if scaler.features.len() != features {
+            return Err(format!("{} coefficients for {} scaled features", features, scaler.features.len()));
+        }
+    }
+    if let Some(interval_stats) = &model.interval_stats {
+        if interval_stats.means.len() != features {
+            return Err(format!("{} coefficients for {} interval features", features, interval_stats.means.len()));
+        }
+    }
+    Ok((Format::Current, model))
+}
+
+fn read_bytes(path: &Path) -> Result<Vec<u8>, String> {
+    let mut file = OpenOptions::new().read(true).open(path)
+        .map_err(|err| format!("theta file {} could not be opened with read permission: {}", path.display(), err))?;
+    let mut bytes: Vec<u8> = vec![];
+    file.read_to_end(&mut bytes).map_err(|err| format!("theta file {} could not be read: {}", path.display(), err))?;
+    Ok(bytes)
+}
+
+/// Reads a model file, only warning about the legacy format
+pub fn load_theta(path: &Path) -> Result<Model, String> {
+    let bytes = read_bytes(path)?;
+    match decode(&bytes) {
+        Ok((Format::Current, model)) => Ok(model),
+        Ok((Format::Legacy, model)) => {
+            println!("Warning: theta file {} uses the legacy 16 byte format, run migrate_theta to upgrade it", path.display());
+            Ok(model)
         }
+        Err(err) => Err(format!("theta file {} is invalid: {}", path.display(), err)),
+    }
+}
+
+fn read_theta(path: Option<&Path>) -> Result<Model, ()> {
+    fn read(path: &Path) -> Result<Model, ()> {
+        load_theta(path).map_err(|err| println!("Error: {}", err))
     }
     if let Some(path) = path {
         read(path)
     } else {
-        let file_path = Path::new("./theta");
+        let file_path = Path::new(DEFAULT_PATH);
         if file_path.exists() {
             read(file_path)
         } else {
-            Err(println!("Warning: default Theta file {} not found", file_path.display()))
+            println!("Warning: default Theta file {} not found", file_path.display());
+            Err(())
         }
     }
 }
 
-pub fn get_theta(path: Option<&Path>) -> (f64, f64) {
+pub fn get_theta(path: Option<&Path>) -> Model {
     read_theta(path).unwrap_or_else(|_| {
         println!("Setting Theta to (0, 0) due to error");
-        (0.0, 0.0)
+        Model::default()
     })
 }
 
-pub fn save_theta(path: Option<&Path>, theta: (f64, f64)) -> Result<(), ()> {
-    fn write(path: &Path, theta: (f64, f64)) -> Result<(), ()> {
-        let mut file =  OpenOptions::new().write(true).create(true).open(path)
-            .map_err(|err| println!("Error: Theta file {} could not be opened or created for write: {}", path.display(), err))?;
-        #[inline]
-        fn chk(err: Result<(), impl Error>) -> Result<(), ()> {
-            if let Err(err) = err {
-                println!("Error: could not write theta to file: {}", err);
-                Err(())
-            } else {
-                Ok(())
+fn write(path: &Path, model: &Model) -> Result<(), String> {
+    let mut file =  OpenOptions::new().write(true).create(true).truncate(true).open(path)
+        .map_err(|err| format!("Theta file {} could not be opened or created for write: {}", path.display(), err))?;
+    file.write_all(&encode(model)).map_err(|err| format!("could not write theta to file: {}", err))
+}
+
+pub fn save_theta(path: Option<&Path>, model: &Model) -> Result<(), String> {
+    write(path.unwrap_or_else(|| Path::new(DEFAULT_PATH)), model)
+}
+
+/// Writes next to `path` then renames over it, an interrupted write leaves the previous checkpoint intact
+pub fn save_checkpoint(path: &Path, model: &Model) -> Result<(), String> {
+    let temporary = path.with_extension("tmp");
+    write(&temporary, model)?;
+    fs::rename(&temporary, path).map_err(|err| format!("could not move checkpoint to {}: {}", path.display(), err))
+}
+
+/// Rewrites a legacy 16 byte theta file in the current format, keeping the original next to it with a `.legacy` extension.
+/// Files already in the current format are left untouched.
+pub fn migrate_theta(path: Option<&Path>) -> Result<(), String> {
+    let path = path.unwrap_or_else(|| Path::new(DEFAULT_PATH));
+    let bytes = read_bytes(path)?;
+    match decode(&bytes) {
+        Ok((Format::Current, _)) => {
+            println!("Theta file {} is already in format version {}", path.display(), FORMAT_VERSION);
+            Ok(())
+        }
+        Ok((Format::Legacy, model)) => {
+            let backup = path.with_extension("legacy");
+            fs::write(&backup, &bytes).map_err(|err| format!("could not back up theta file to {}: {}", backup.display(), err))?;
+            write(path, &model)?;
+            println!("Migrated theta file {} to format version {}, the original was kept as {}", path.display(), FORMAT_VERSION, backup.display());
+            Ok(())
+        }
+        Err(err) => Err(format!("theta file {} is invalid: {}", path.display(), err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::{Dataset, DatasetEntry};
+    use crate::metrics::Metrics;
+    use crate::optimizer::OptimizerKind;
+    use crate::scaler::ScalerKind;
+    use crate::schedule::Schedule;
+    use crate::solver::Solver;
+
+    fn model() -> Model {
+        let dataset = Dataset {
+            features: vec!["km".into(), "age".into()],
+            entries: vec![
+                DatasetEntry { features: vec![240000.0, 12.0], price: 3650.0 },
+                DatasetEntry { features: vec![139800.0, 8.0], price: 3800.0 },
+                DatasetEntry { features: vec![48235.0, 2.0], price: 6900.0 },
+                DatasetEntry { features: vec![84000.0, 6.0], price: 6200.0 },
+            ],
+        };
+        let mut model = Model {
+            theta: vec![0.5, -0.25, 0.125],
+            features: dataset.features.clone(),
+            scaler: Some(Scaler::fit(ScalerKind::ZScore, &dataset)),
+            training: Some(TrainingSummary { solver: Solver::GradientDescent, iterations: 42, seconds: 0.5, stop: None }),
+            batching: Some(Batching { batch_size: 2, seed: 7 }),
+            penalty: Penalty::Ridge { lambda: 0.01 },
+            checkpoint: Some(Checkpoint {
+                iterations: 42,
+                seconds: 0.5,
+                last_cost: 1.25,
+                ratio: 0.1,
+                optimizer: OptimizerKind::Adam { beta1: 0.9, beta2: 0.999, epsilon: 1e-8 },
+                schedule: Schedule::Cosine { period: 100, min_ratio: 1e-3 },
+                penalty: Penalty::Ridge { lambda: 0.01 },
+                optimizer_state: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0],
+                shuffle: Some((7, vec![2, 0, 1])),
+            }),
+            partial: true,
+            split: Some(Split { test_fraction: 0.2, seed: 3 }),
+            evaluation: Some(Evaluation { train: Metrics::compute(&[(1.0, 2.0), (3.0, 3.0)], 0), test: Some(Metrics::compute(&[(2.0, 2.5), (4.0, 3.0)], 0)) }),
+            interval_stats: None,
+            range: TrainingRange::compute(&dataset),
+        };
+        model.interval_stats = Some(IntervalStats::compute(&model, &dataset).unwrap());
+        model
+    }
+
+    fn error(bytes: &[u8]) -> String {
+        match decode(bytes) {
+            Ok(_) => panic!("decoded"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for model in [Model::default(), model()] {
+            match decode(&encode(&model)) {
+                Ok((Format::Current, decoded)) => assert_eq!(decoded, model),
+                Ok((Format::Legacy, _)) => panic!("decoded as legacy"),
+                Err(err) => panic!("{}", err),
             }
         }
-        chk(file.write_all(&theta.0.to_be_bytes()))?;
-        chk(file.write_all(&theta.1.to_be_bytes()))
     }
-    if let Some(file) = path {
-        write(file, theta)
-    } else {
-        let file_path = Path::new("./theta");
-        write(file_path, theta)
+
+    #[test]
+    fn header_holds_raw_theta() {
+        let model = model();
+        let bytes = encode(&model);
+        let mut reader = ByteReader::new(&bytes[MAGIC.len() + 2..]);
+        assert_eq!(reader.u32("feature count"), Ok(2));
+        let header: Vec<_> = (0..3).map(|_| reader.f64("theta").unwrap()).collect();
+        assert_eq!(header, model.raw_theta());
+    }
+
+    #[test]
+    fn legacy() {
+        let mut bytes = 1.5f64.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&(-0.5f64).to_be_bytes());
+        match decode(&bytes) {
+            Ok((Format::Legacy, model)) => assert_eq!(model, Model { theta: vec![1.5, -0.5], ..Model::default() }),
+            _ => panic!("not decoded as legacy"),
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rejects_corruption() {
+        let bytes = encode(&model());
+        for idx in [MAGIC.len(), MAGIC.len() + 10, bytes.len() / 2, bytes.len() - 1] {
+            let mut corrupted = bytes.clone();
+            corrupted[idx] ^= 0x10;
+            assert!(error(&corrupted).contains("checksum mismatch"), "byte {}", idx);
+        }
+    }
+
+    #[test]
+    fn rejects_truncation() {
+        let bytes = encode(&model());
+        for len in [0, 3, MAGIC.len() + 2, 16, bytes.len() / 2, bytes.len() - 1] {
+            error(&bytes[..len]);
+        }
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let mut bytes = encode(&Model::default());
+        bytes[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(FORMAT_VERSION + 1).to_be_bytes());
+        let len = bytes.len() - 4;
+        let checksum = crc32(&bytes[..len]);
+        bytes[len..].copy_from_slice(&checksum.to_be_bytes());
+        assert!(error(&bytes).contains("not supported"));
+    }
+
+    #[test]
+    fn skips_unknown_sections() {
+        let mut bytes = encode(&Model::default());
+        bytes.truncate(bytes.len() - 4);
+        // the section count follows the magic, version, feature count and two coefficients
+        let count_at = MAGIC.len() + 2 + 4 + 16;
+        let count = u32::from_be_bytes(bytes[count_at..count_at + 4].try_into().unwrap());
+        bytes[count_at..count_at + 4].copy_from_slice(&(count + 1).to_be_bytes());
+        bytes.extend_from_slice(b"XTRA");
+        put_u32(&mut bytes, 3);
+        bytes.extend_from_slice(&[1, 2, 3]);
+        let checksum = crc32(&bytes);
+        put_u32(&mut bytes, checksum);
+        assert_eq!(decode(&bytes).map(|(_, model)| model), Ok(Model::default()));
+    }
+}
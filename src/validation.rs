@@ -0,0 +1,133 @@
+use crate::args::{UsizeParser, BoolParser, DefaultArgParser};
+use crate::dataset::Dataset;
+use crate::metrics::Metrics;
+use crate::random::Random;
+use crate::theta::Model;
+
+pub struct FoldsArg;
+
+impl UsizeParser<'_> for FoldsArg {
+    const NAMES: &'static [&'static str] = &["-k", "--folds"];
+    const DESCRIPTION: &'static str = "Number of folds, each row is tested once per repeat";
+}
+
+impl DefaultArgParser<'_, usize> for FoldsArg {
+    const DEFAULT: usize = 5;
+}
+
+pub struct RepeatsArg;
+
+impl UsizeParser<'_> for RepeatsArg {
+    const NAMES: &'static [&'static str] = &["--repeats"];
+    const DESCRIPTION: &'static str = "Runs k-fold this many times, with a different shuffle each time";
+}
+
+impl DefaultArgParser<'_, usize> for RepeatsArg {
+    const DEFAULT: usize = 1;
+}
+
+pub struct LeaveOneOutArg;
+
+impl BoolParser<'_> for LeaveOneOutArg {
+    const NAMES: &'static [&'static str] = &["--leave-one-out", "--loo"];
+    const DESCRIPTION: &'static str = "One fold per row, replaces --folds and --repeats";
+}
+
+/// A model trained without one fold, evaluated on it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fold {
+    pub repeat: usize,
+    pub fold: usize,
+    pub train: Metrics,
+    pub test: Metrics,
+    /// (actual, predicted) price of each tested row
+    pub predictions: Vec<(f64, f64)>,
+}
+
+/// Row indices of each of `k` folds, shuffled by `random`. Fold sizes differ by one at most.
+pub fn k_fold(rows: usize, k: usize, random: &mut Random) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..rows).collect();
+    random.shuffle(&mut order);
+    let mut folds = vec![vec![]; k];
+    order.into_iter().enumerate().for_each(|(idx, row)| folds[idx % k].push(row));
+    folds.iter_mut().for_each(|it| it.sort_unstable());
+    folds
+}
+
+/// Trains with `train` on all rows but one fold, for each fold of each repeat. Leave-one-out is `k` = row count with one repeat.
+pub fn cross_validate(dataset: &Dataset, k: usize, repeats: usize, seed: u64, train: impl Fn(&Dataset) -> Result<Model, String>) -> Result<Vec<Fold>, String> {
+    if k < 2 || k > dataset.entries.len() {
+        return Err(format!("cannot make {} folds out of {} rows", k, dataset.entries.len()));
+    }
+    let mut random = Random::new(seed);
+    let mut results = vec![];
+    for repeat in 0..repeats {
+        for (fold, rows) in k_fold(dataset.entries.len(), k, &mut random).into_iter().enumerate() {
+            let mut tested = vec![false; dataset.entries.len()];
+            rows.iter().for_each(|it| tested[*it] = true);
+            let (test, rest): (Vec<_>, Vec<_>) = dataset.entries.iter().zip(tested).partition(|(_, tested)| *tested);
+            let subset = |entries: Vec<_>| Dataset { features: dataset.features.clone(), entries: entries.into_iter().map(|(it, _)| it).cloned().collect() };
+            let (test, rest) = (subset(test), subset(rest));
+            let model = train(&rest).map_err(|err| format!("repeat {} fold {}: {}", repeat + 1, fold + 1, err))?;
+            let predictions: Vec<_> = test.entries.iter().map(|it| (it.price, model.predict(&it.features))).collect();
+            results.push(Fold { repeat, fold, train: Metrics::of(&model, &rest), test: Metrics::compute(&predictions, dataset.features.len()), predictions });
+        }
+    }
+    Ok(results)
+}
+
+/// Mean and sample standard deviation of each metric, leaving out the NaN ones, like R² on a single row fold.
+/// The count is the mean count.
+pub fn mean_std(metrics: &[Metrics]) -> (Metrics, Metrics) {
+    let (mut mean, mut std) = ([0.0; 8], [0.0; 8]);
+    for idx in 0..8 {
+        let values: Vec<_> = metrics.iter().map(|it| it.values()[idx]).filter(|it| !it.is_nan()).collect();
+        mean[idx] = values.iter().sum::<f64>() / values.len() as f64;
+        let variance = values.iter().map(|it| (it - mean[idx]) * (it - mean[idx])).sum::<f64>() / (values.len() as f64 - 1.0);
+        std[idx] = if values.len() > 1 { variance.sqrt() } else { f64::NAN };
+    }
+    let count = (metrics.iter().map(|it| it.count).sum::<usize>() as f64 / metrics.len() as f64).round() as usize;
+    (Metrics::from_values(count, mean), Metrics::from_values(count, std))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::DatasetEntry;
+
+    fn dataset(rows: usize) -> Dataset {
+        Dataset {
+            features: vec!["km".into()],
+            entries: (0..rows).map(|idx| DatasetEntry { features: vec![idx as f64], price: 1.0 + 2.0 * idx as f64 }).collect(),
+        }
+    }
+
+    #[test]
+    fn folds_cover_every_row_once() {
+        let folds = k_fold(23, 5, &mut Random::new(3));
+        assert_eq!(folds.len(), 5);
+        assert!(folds.iter().all(|it| it.len() == 4 || it.len() == 5));
+        let mut rows: Vec<_> = folds.concat();
+        rows.sort_unstable();
+        assert_eq!(rows, (0..23).collect::<Vec<_>>());
+        assert_eq!(folds, k_fold(23, 5, &mut Random::new(3)));
+    }
+
+    #[test]
+    fn every_repeat_tests_every_row() {
+        let dataset = dataset(10);
+        let exact = |_: &Dataset| Ok(Model { theta: vec![1.0, 2.0], features: vec!["km".into()], ..Model::default() });
+        let results = cross_validate(&dataset, 3, 2, 7, exact).unwrap();
+        assert_eq!(results.len(), 6);
+        for repeat in 0..2 {
+            let mut tested: Vec<_> = results.iter().filter(|it| it.repeat == repeat).flat_map(|it| it.predictions.iter().map(|it| it.0)).collect();
+            tested.sort_by(f64::total_cmp);
+            assert_eq!(tested, dataset.entries.iter().map(|it| it.price).collect::<Vec<_>>());
+        }
+        assert!(results.iter().all(|it| it.train.count + it.test.count == 10 && it.test.mse == 0.0));
+        let leave_one_out = cross_validate(&dataset, 10, 1, 7, exact).unwrap();
+        assert!(leave_one_out.iter().all(|it| it.test.count == 1));
+        assert!(cross_validate(&dataset, 11, 1, 7, exact).is_err());
+        assert!(cross_validate(&dataset, 1, 1, 7, exact).is_err());
+    }
+}